@@ -0,0 +1,74 @@
+use miniature::datasets::MNISTLoader;
+use miniature::functions as F;
+use miniature::graph::{backward, no_grad};
+use miniature::optimizers as S;
+use miniature::parametric_functions as PF;
+use miniature::random;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    random::seed(0);
+
+    let dataset = MNISTLoader::new("datasets")?;
+    let (test_x, test_t) = dataset.get_test_data();
+    let test_size = test_x.borrow().shape[0];
+
+    // LeNet-style network
+    let conv1 = PF::conv2d(1, 6, (5, 5), (2, 2), (2, 2), (1, 1), 1);
+    let conv2 = PF::conv2d(6, 16, (5, 5), (2, 2), (0, 0), (1, 1), 1);
+    let fc1 = PF::linear(16 * 5 * 5, 120);
+    let fc2 = PF::linear(120, 10);
+
+    let mut optim = S::adam(0.001, (0.9, 0.999), 1e-8);
+    optim.set_params(conv1.get_params());
+    optim.set_params(conv2.get_params());
+    optim.set_params(fc1.get_params());
+    optim.set_params(fc2.get_params());
+
+    let mut iter = 0;
+    loop {
+        let (x, t) = dataset.sample(32);
+        let onehot_t = F::onehot(t, 10);
+
+        // forward
+        let x = F::reshape(x, vec![32, 1, 28, 28]);
+        let h = F::relu(conv1.call(x));
+        let h = F::relu(conv2.call(h));
+        let h = F::relu(fc1.call(F::reshape(h, vec![32, 16 * 5 * 5])));
+        let output = fc2.call(h);
+
+        // loss
+        let loss = F::cross_entropy_loss(output, onehot_t);
+
+        optim.zero_grad();
+        backward(loss);
+        optim.update();
+
+        iter += 1;
+        if iter % 100 == 0 {
+            // test without building the graph
+            let _guard = no_grad();
+            let x = F::reshape(test_x.clone(), vec![test_size, 1, 28, 28]);
+            let h = F::relu(conv1.call(x));
+            let h = F::relu(conv2.call(h));
+            let h = F::relu(fc1.call(F::reshape(h, vec![test_size, 16 * 5 * 5])));
+            let output = F::argmax(fc2.call(h));
+
+            let mut count = 0;
+            for i in 0..test_size {
+                let pred_label = output.borrow().data[i] as u8;
+                let test_label = test_t.borrow().data[i] as u8;
+                if pred_label == test_label {
+                    count += 1;
+                }
+            }
+            let accuracy = (count as f32) / (test_size as f32);
+            println!("Iteration {}: Accuracy={}", iter, accuracy);
+        }
+
+        if iter == 10000 {
+            break;
+        }
+    }
+
+    Ok(())
+}
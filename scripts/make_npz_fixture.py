@@ -0,0 +1,58 @@
+# writes testdata/savez.npz used by the npz reader tests
+#
+# uses numpy.savez when numpy is installed, and otherwise the same zipfile
+# calls and npy headers as numpy.savez
+import struct
+import sys
+import zipfile
+
+PATH = "testdata/savez.npz"
+
+ARRAYS = {
+    "a": ("<f4", (2, 3), [0.5, 1.0, 1.5, 2.0, 2.5, 3.0]),
+    "b": ("<f8", (3,), [-1.5, 0.25, 1024.0]),
+    "c": ("<i8", (), [7]),
+}
+
+
+def savez_numpy():
+    import numpy as np
+
+    arrays = {
+        name: np.array(values, dtype=descr).reshape(shape)
+        for name, (descr, shape, values) in ARRAYS.items()
+    }
+    np.savez(PATH, **arrays)
+
+
+def npy(descr, shape, values):
+    # numpy.lib.format.write_array with version 1.0
+    header = "{'descr': %r, 'fortran_order': False, 'shape': %r, }" % (descr, shape)
+    if shape:
+        header += " " * (21 - len(repr(shape[0])))
+    header = header.encode("latin1")
+    length = len(header) + 1
+    padding = 64 - (8 + 2 + length) % 64
+    prefix = b"\x93NUMPY\x01\x00" + struct.pack("<H", length + padding)
+    code = {"<f4": "<f", "<f8": "<d", "<i8": "<q"}[descr]
+    data = b"".join(struct.pack(code, value) for value in values)
+    return prefix + header + b" " * padding + b"\n" + data
+
+
+def savez_zipfile():
+    with zipfile.ZipFile(PATH, mode="w", compression=zipfile.ZIP_STORED, allowZip64=True) as zipf:
+        for name, array in ARRAYS.items():
+            with zipf.open(name + ".npy", "w", force_zip64=True) as fid:
+                fid.write(npy(*array))
+
+
+def main():
+    try:
+        savez_numpy()
+    except ImportError:
+        print("numpy is not installed, writing with zipfile", file=sys.stderr)
+        savez_zipfile()
+
+
+if __name__ == "__main__":
+    main()
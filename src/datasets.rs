@@ -4,6 +4,7 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::rc::Rc;
 
+use crate::random;
 use crate::variable::Variable;
 
 const MNIST_IMAGE_SIZE: usize = 28 * 28;
@@ -37,10 +38,10 @@ fn load_mnist_image_file(path: &str) -> Result<Vec<Vec<f32>>, Box<dyn std::error
     let mut images: Vec<Vec<f32>> = Vec::new();
     for _ in 0..total_number {
         let mut image = vec![0.0; (height * width) as usize];
-        for i in 0..(height * width) as usize {
+        for pixel in image.iter_mut() {
             let mut buf = [0; 1];
             file.read_exact(&mut buf)?;
-            image[i] = u8::from_be_bytes(buf) as f32 / 255.0;
+            *pixel = u8::from_be_bytes(buf) as f32 / 255.0;
         }
         images.push(image);
     }
@@ -63,10 +64,10 @@ fn load_mnist_label_file(path: &str) -> Result<Vec<i32>, Box<dyn std::error::Err
 
     // read label data
     let mut labels: Vec<i32> = vec![0; total_number as usize];
-    for i in 0..total_number as usize {
+    for label in labels.iter_mut() {
         let mut buf = [0; 1];
         file.read_exact(&mut buf)?;
-        labels[i] = u8::from_be_bytes(buf) as i32;
+        *label = u8::from_be_bytes(buf) as i32;
     }
 
     Ok(labels)
@@ -115,12 +116,19 @@ impl MNISTLoader {
     }
 
     pub fn sample(&self, batch_size: usize) -> (Rc<RefCell<Variable>>, Rc<RefCell<Variable>>) {
+        random::with_rng(|rng| self.sample_with(batch_size, rng))
+    }
+
+    pub fn sample_with<R: Rng>(
+        &self,
+        batch_size: usize,
+        rng: &mut R,
+    ) -> (Rc<RefCell<Variable>>, Rc<RefCell<Variable>>) {
         let mut images = vec![0.0; batch_size * MNIST_IMAGE_SIZE];
         let mut labels = vec![0.0; batch_size];
 
-        let mut rng = rand::thread_rng();
-        for i in 0..batch_size {
-            let index = rng.gen_range(0, self.train_size) as usize;
+        for (i, label) in labels.iter_mut().enumerate() {
+            let index = rng.gen_range(0, self.train_size);
 
             // set image
             let image_start = MNIST_IMAGE_SIZE * i;
@@ -128,7 +136,7 @@ impl MNISTLoader {
             images[image_start..image_end].copy_from_slice(&self.train_images[index]);
 
             // set label
-            labels[i] = self.train_labels[index] as f32;
+            *label = self.train_labels[index] as f32;
         }
 
         let image_batch = Rc::new(RefCell::new(Variable::new(vec![
@@ -19,11 +19,32 @@ impl std::fmt::Debug for dyn FunctionImpl {
     }
 }
 
+// stands in for a freed function so that its buffers (e.g. masks or
+// indices saved for backward) are dropped while the name is kept for errors
+struct Freed {
+    name: String,
+}
+
+impl FunctionImpl for Freed {
+    fn forward_impl(&mut self, _: &[Rc<RefCell<Variable>>], _: &[Rc<RefCell<Variable>>]) {
+        unreachable!("{} has been freed", self.name);
+    }
+
+    fn backward_impl(&mut self, _: &[Rc<RefCell<Variable>>], _: &[Rc<RefCell<Variable>>]) {
+        unreachable!("{} has been freed", self.name);
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
 #[derive(Debug)]
 pub struct CgFunction {
     inputs: Vec<Rc<RefCell<Variable>>>,
     outputs: Vec<Rc<RefCell<Variable>>>,
     function_impl: Box<dyn FunctionImpl>,
+    freed: bool,
 }
 
 impl CgFunction {
@@ -36,6 +57,7 @@ impl CgFunction {
             inputs,
             outputs,
             function_impl,
+            freed: false,
         }
     }
 
@@ -44,10 +66,41 @@ impl CgFunction {
     }
 
     pub fn backward(&mut self) {
+        self.check_not_freed();
         self.function_impl
             .backward_impl(&self.inputs, &self.outputs);
     }
 
+    // drops links to inputs and outputs so that intermediate variables are
+    // released, which also breaks the reference cycle with the outputs, and
+    // drops the state of the function itself
+    pub fn free(&mut self) {
+        if self.freed {
+            return;
+        }
+        self.inputs = vec![];
+        self.outputs = vec![];
+        let name = self.function_impl.get_name().to_string();
+        self.function_impl = Box::new(Freed { name });
+        self.freed = true;
+    }
+
+    pub fn is_freed(&self) -> bool {
+        self.freed
+    }
+
+    pub fn check_not_freed(&self) {
+        assert!(
+            !self.freed,
+            "backward through {} whose graph has already been freed; use retain_graph to call backward twice",
+            self.function_impl.get_name()
+        );
+    }
+
+    pub fn get_outputs(&self) -> &Vec<Rc<RefCell<Variable>>> {
+        &self.outputs
+    }
+
     pub fn get_inputs(&self) -> &Vec<Rc<RefCell<Variable>>> {
         &self.inputs
     }
@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use super::broadcast::{broadcast_indices, broadcast_shape};
 use crate::function::FunctionImpl;
 use crate::variable::Variable;
 
@@ -13,11 +14,10 @@ impl Add {
         assert_eq!(outputs.len(), 1);
 
         let x = inputs[0].borrow();
-        let y = inputs[0].borrow();
+        let y = inputs[1].borrow();
         let output = outputs[0].borrow();
 
-        assert_eq!(x.shape, y.shape);
-        assert_eq!(x.shape, output.shape);
+        assert_eq!(broadcast_shape(&x.shape, &y.shape), output.shape);
     }
 }
 
@@ -33,8 +33,10 @@ impl FunctionImpl for Add {
         let y = inputs[1].borrow();
         let mut output = outputs[0].borrow_mut();
 
-        for i in 0..x.size() {
-            output.data[i] = x.data[i] + y.data[i];
+        let x_indices = broadcast_indices(&x.shape, &output.shape);
+        let y_indices = broadcast_indices(&y.shape, &output.shape);
+        for i in 0..output.size() {
+            output.data[i] = x.data[x_indices[i]] + y.data[y_indices[i]];
         }
     }
 
@@ -45,13 +47,30 @@ impl FunctionImpl for Add {
     ) {
         self.validate(inputs, outputs);
 
-        let mut x = inputs[0].borrow_mut();
-        let mut y = inputs[1].borrow_mut();
-        let output = outputs[0].borrow();
+        // compute gradients first since x and y can be the same variable
+        let (x_grad, y_grad) = {
+            let x = inputs[0].borrow();
+            let y = inputs[1].borrow();
+            let output = outputs[0].borrow();
 
-        for i in 0..x.size() {
-            x.grad[i] += output.grad[i];
-            y.grad[i] += output.grad[i];
+            // broadcasted dimensions are reduced by accumulation
+            let x_indices = broadcast_indices(&x.shape, &output.shape);
+            let y_indices = broadcast_indices(&y.shape, &output.shape);
+            let mut x_grad = vec![0.0; x.size()];
+            let mut y_grad = vec![0.0; y.size()];
+            for i in 0..output.size() {
+                let (xi, yi) = (x_indices[i], y_indices[i]);
+                x_grad[xi] += output.grad[i];
+                y_grad[yi] += output.grad[i];
+            }
+            (x_grad, y_grad)
+        };
+
+        for (g, v) in inputs[0].borrow_mut().grad.iter_mut().zip(x_grad) {
+            *g += v;
+        }
+        for (g, v) in inputs[1].borrow_mut().grad.iter_mut().zip(y_grad) {
+            *g += v;
         }
     }
 
@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct AvgPool2d {
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+}
+
+impl AvgPool2d {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        // supports only NCHW layout
+        assert_eq!(x.shape.len(), 4);
+        assert_eq!(output.shape, self.output_shape(&x.shape));
+    }
+
+    pub fn output_shape(&self, x_shape: &[usize]) -> Vec<usize> {
+        assert!(self.stride.0 > 0 && self.stride.1 > 0);
+        let padded_height = x_shape[2] + 2 * self.padding.0;
+        let padded_width = x_shape[3] + 2 * self.padding.1;
+        assert!(padded_height >= self.kernel_size.0 && padded_width >= self.kernel_size.1);
+        vec![
+            x_shape[0],
+            x_shape[1],
+            (padded_height - self.kernel_size.0) / self.stride.0 + 1,
+            (padded_width - self.kernel_size.1) / self.stride.1 + 1,
+        ]
+    }
+
+    // calls f(input index, output index) for every pixel inside each window
+    fn for_each_window<T: FnMut(usize, usize)>(
+        &self,
+        x_shape: &[usize],
+        output_shape: &[usize],
+        mut f: T,
+    ) {
+        let (height, width) = (x_shape[2] as isize, x_shape[3] as isize);
+        let (output_height, output_width) = (output_shape[2], output_shape[3]);
+        let planes = x_shape[0] * x_shape[1];
+
+        for p in 0..planes {
+            let plane_offset = p * (height * width) as usize;
+            for oh in 0..output_height {
+                for ow in 0..output_width {
+                    let out_index = (p * output_height + oh) * output_width + ow;
+                    for kh in 0..self.kernel_size.0 {
+                        for kw in 0..self.kernel_size.1 {
+                            let h = (oh * self.stride.0 + kh) as isize - self.padding.0 as isize;
+                            let w = (ow * self.stride.1 + kw) as isize - self.padding.1 as isize;
+                            if h < 0 || h >= height || w < 0 || w >= width {
+                                continue;
+                            }
+                            f(plane_offset + (h * width + w) as usize, out_index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FunctionImpl for AvgPool2d {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        // padded pixels count as zeros
+        let area = (self.kernel_size.0 * self.kernel_size.1) as f32;
+        let output_shape = output.shape.clone();
+        output.zeros();
+        self.for_each_window(&x.shape, &output_shape, |i, j| {
+            output.data[j] += x.data[i] / area;
+        });
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        let area = (self.kernel_size.0 * self.kernel_size.1) as f32;
+        let x_shape = x.shape.clone();
+        self.for_each_window(&x_shape, &output.shape, |i, j| {
+            x.grad[i] += output.grad[j] / area;
+        });
+    }
+
+    fn get_name(&self) -> &str {
+        "AvgPool2d"
+    }
+}
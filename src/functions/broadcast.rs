@@ -4,6 +4,62 @@ use std::rc::Rc;
 use crate::function::FunctionImpl;
 use crate::variable::Variable;
 
+// NumPy-style broadcasting: shapes are right-aligned and size-1 dimensions
+// are expanded
+pub fn broadcast_shape(x_shape: &[usize], y_shape: &[usize]) -> Vec<usize> {
+    let ndim = x_shape.len().max(y_shape.len());
+    let x_offset = ndim - x_shape.len();
+    let y_offset = ndim - y_shape.len();
+
+    let mut shape = vec![0; ndim];
+    for (i, dim_size) in shape.iter_mut().enumerate() {
+        let x_dim = if i < x_offset {
+            1
+        } else {
+            x_shape[i - x_offset]
+        };
+        let y_dim = if i < y_offset {
+            1
+        } else {
+            y_shape[i - y_offset]
+        };
+        assert!(
+            x_dim == y_dim || x_dim == 1 || y_dim == 1,
+            "shapes {:?} and {:?} cannot be broadcast",
+            x_shape,
+            y_shape
+        );
+        *dim_size = if x_dim == 1 { y_dim } else { x_dim };
+    }
+    shape
+}
+
+// maps each element of the broadcasted tensor to the element of the input
+pub fn broadcast_indices(in_shape: &[usize], out_shape: &[usize]) -> Vec<usize> {
+    let offset = out_shape.len() - in_shape.len();
+
+    // expanded dimensions have zero strides
+    let mut strides = vec![0; out_shape.len()];
+    let mut stride = 1;
+    for i in (0..in_shape.len()).rev() {
+        if in_shape[i] != 1 {
+            strides[i + offset] = stride;
+        }
+        stride *= in_shape[i];
+    }
+
+    let size = out_shape.iter().product();
+    let mut indices = vec![0; size];
+    for (i, index) in indices.iter_mut().enumerate() {
+        let mut rest = i;
+        for d in (0..out_shape.len()).rev() {
+            *index += (rest % out_shape[d]) * strides[d];
+            rest /= out_shape[d];
+        }
+    }
+    indices
+}
+
 #[derive(Debug)]
 pub struct Broadcast {
     pub shape: Vec<usize>,
@@ -17,15 +73,9 @@ impl Broadcast {
         let x = inputs[0].borrow();
         let output = outputs[0].borrow();
 
-        assert_eq!(x.shape.len(), self.shape.len());
+        assert!(x.shape.len() <= self.shape.len());
         assert_eq!(output.shape, self.shape);
-        for i in 0..x.shape.len() {
-            if x.shape[i] != 1 {
-                assert_eq!(x.shape[i], self.shape[i]);
-            } else {
-                assert_eq!(x.shape[i], 1);
-            }
-        }
+        assert_eq!(broadcast_shape(&x.shape, &self.shape), self.shape);
     }
 }
 
@@ -40,12 +90,9 @@ impl FunctionImpl for Broadcast {
         let x = inputs[0].borrow();
         let mut output = outputs[0].borrow_mut();
 
-        // supports only batch dimension
-        for i in 0..output.shape[0] {
-            let offset = i * x.size();
-            for j in 0..x.size() {
-                output.data[j + offset] = x.data[j];
-            }
+        let indices = broadcast_indices(&x.shape, &self.shape);
+        for (i, index) in indices.iter().enumerate() {
+            output.data[i] = x.data[*index];
         }
     }
 
@@ -59,12 +106,9 @@ impl FunctionImpl for Broadcast {
         let mut x = inputs[0].borrow_mut();
         let output = outputs[0].borrow();
 
-        // supports only batch dimension
-        for i in 0..x.size() {
-            for j in 0..output.shape[0] {
-                let offset = j * x.size();
-                x.grad[i] += output.grad[i + offset];
-            }
+        let indices = broadcast_indices(&x.shape, &self.shape);
+        for (i, index) in indices.iter().enumerate() {
+            x.grad[*index] += output.grad[i];
         }
     }
 
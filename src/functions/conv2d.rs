@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::matmul::{matmul_impl, transpose};
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct Conv2d {
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    pub groups: usize,
+}
+
+impl Conv2d {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert!(inputs.len() == 2 || inputs.len() == 3);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let weight = inputs[1].borrow();
+        let output = outputs[0].borrow();
+
+        // supports only NCHW layout
+        assert_eq!(x.shape.len(), 4);
+        assert_eq!(weight.shape.len(), 4);
+        assert_eq!(x.shape[1] % self.groups, 0);
+        assert_eq!(weight.shape[0] % self.groups, 0);
+        assert_eq!(x.shape[1] / self.groups, weight.shape[1]);
+        assert_eq!(output.shape, self.output_shape(&x.shape, &weight.shape));
+
+        if inputs.len() == 3 {
+            let bias = inputs[2].borrow();
+            assert_eq!(bias.shape, vec![weight.shape[0]]);
+        }
+    }
+
+    pub fn output_shape(&self, x_shape: &[usize], weight_shape: &[usize]) -> Vec<usize> {
+        assert!(self.stride.0 > 0 && self.stride.1 > 0);
+        let dilated_height = self.dilation.0 * (weight_shape[2] - 1) + 1;
+        let dilated_width = self.dilation.1 * (weight_shape[3] - 1) + 1;
+        let padded_height = x_shape[2] + 2 * self.padding.0;
+        let padded_width = x_shape[3] + 2 * self.padding.1;
+        assert!(padded_height >= dilated_height && padded_width >= dilated_width);
+        vec![
+            x_shape[0],
+            weight_shape[0],
+            (padded_height - dilated_height) / self.stride.0 + 1,
+            (padded_width - dilated_width) / self.stride.1 + 1,
+        ]
+    }
+
+    // maps each element of the column matrix (channels * kernel, output pixels)
+    // to the index of the input image, or None for padded pixels
+    fn column_indices(
+        &self,
+        x_shape: &[usize],
+        weight_shape: &[usize],
+        output_shape: &[usize],
+    ) -> Vec<Option<usize>> {
+        let (height, width) = (x_shape[2] as isize, x_shape[3] as isize);
+        let (channels, kernel_height, kernel_width) =
+            (weight_shape[1], weight_shape[2], weight_shape[3]);
+        let (output_height, output_width) = (output_shape[2], output_shape[3]);
+
+        let mut indices = vec![];
+        for c in 0..channels {
+            for kh in 0..kernel_height {
+                for kw in 0..kernel_width {
+                    for oh in 0..output_height {
+                        for ow in 0..output_width {
+                            let h = (oh * self.stride.0 + kh * self.dilation.0) as isize
+                                - self.padding.0 as isize;
+                            let w = (ow * self.stride.1 + kw * self.dilation.1) as isize
+                                - self.padding.1 as isize;
+                            if h < 0 || h >= height || w < 0 || w >= width {
+                                indices.push(None);
+                            } else {
+                                indices.push(Some(
+                                    c * (height * width) as usize + (h * width + w) as usize,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        indices
+    }
+}
+
+impl FunctionImpl for Conv2d {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let weight = inputs[1].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let batch_size = x.shape[0];
+        let group_channels = weight.shape[1];
+        let group_out_channels = weight.shape[0] / self.groups;
+        let image_size = x.shape[1] * x.shape[2] * x.shape[3];
+        let group_image_size = group_channels * x.shape[2] * x.shape[3];
+        let output_pixels = output.shape[2] * output.shape[3];
+        let kernel_size = group_channels * weight.shape[2] * weight.shape[3];
+
+        let indices = self.column_indices(&x.shape, &weight.shape, &output.shape);
+        let mut column = vec![0.0; indices.len()];
+
+        // set zeros in output tensor
+        output.zeros();
+
+        for n in 0..batch_size {
+            for g in 0..self.groups {
+                // im2col
+                let image_offset = n * image_size + g * group_image_size;
+                for (value, index) in column.iter_mut().zip(indices.iter()) {
+                    *value = index.map_or(0.0, |i| x.data[image_offset + i]);
+                }
+
+                // weight @ column = output
+                let weight_start = g * group_out_channels * kernel_size;
+                let weight_end = weight_start + group_out_channels * kernel_size;
+                let output_start = (n * self.groups + g) * group_out_channels * output_pixels;
+                let output_end = output_start + group_out_channels * output_pixels;
+                matmul_impl(
+                    &weight.data[weight_start..weight_end],
+                    &[group_out_channels, kernel_size],
+                    &column,
+                    &[kernel_size, output_pixels],
+                    &mut output.data[output_start..output_end],
+                );
+            }
+        }
+
+        if inputs.len() == 3 {
+            let bias = inputs[2].borrow();
+            let out_channels = output.shape[1];
+            for (i, value) in output.data.iter_mut().enumerate() {
+                *value += bias.data[(i / output_pixels) % out_channels];
+            }
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        // gradients are computed with shared borrows and added afterwards,
+        // since x and weight may be the same variable
+        let x = inputs[0].borrow();
+        let weight = inputs[1].borrow();
+        let output = outputs[0].borrow();
+        let mut x_grad = vec![0.0; x.size()];
+        let mut weight_grad = vec![0.0; weight.size()];
+
+        let batch_size = x.shape[0];
+        let group_channels = weight.shape[1];
+        let group_out_channels = weight.shape[0] / self.groups;
+        let image_size = x.shape[1] * x.shape[2] * x.shape[3];
+        let group_image_size = group_channels * x.shape[2] * x.shape[3];
+        let output_pixels = output.shape[2] * output.shape[3];
+        let kernel_size = group_channels * weight.shape[2] * weight.shape[3];
+
+        let indices = self.column_indices(&x.shape, &weight.shape, &output.shape);
+        let mut column = vec![0.0; indices.len()];
+        let mut transposed_column = vec![0.0; indices.len()];
+        let mut transposed_weight = vec![0.0; group_out_channels * kernel_size];
+
+        for n in 0..batch_size {
+            for g in 0..self.groups {
+                let image_offset = n * image_size + g * group_image_size;
+                let weight_start = g * group_out_channels * kernel_size;
+                let weight_end = weight_start + group_out_channels * kernel_size;
+                let output_start = (n * self.groups + g) * group_out_channels * output_pixels;
+                let output_end = output_start + group_out_channels * output_pixels;
+                let output_grad = &output.grad[output_start..output_end];
+
+                // gradients for weight
+                // g_out @ column^T = g_weight
+                for (value, index) in column.iter_mut().zip(indices.iter()) {
+                    *value = index.map_or(0.0, |i| x.data[image_offset + i]);
+                }
+                transpose(
+                    &column,
+                    &mut transposed_column,
+                    &[kernel_size, output_pixels],
+                );
+                matmul_impl(
+                    output_grad,
+                    &[group_out_channels, output_pixels],
+                    &transposed_column,
+                    &[output_pixels, kernel_size],
+                    &mut weight_grad[weight_start..weight_end],
+                );
+
+                // gradients for x
+                // weight^T @ g_out = g_column, then col2im
+                transpose(
+                    &weight.data[weight_start..weight_end],
+                    &mut transposed_weight,
+                    &[group_out_channels, kernel_size],
+                );
+                column.fill(0.0);
+                matmul_impl(
+                    &transposed_weight,
+                    &[kernel_size, group_out_channels],
+                    output_grad,
+                    &[group_out_channels, output_pixels],
+                    &mut column,
+                );
+                for (value, index) in column.iter().zip(indices.iter()) {
+                    if let Some(i) = index {
+                        x_grad[image_offset + i] += value;
+                    }
+                }
+            }
+        }
+        drop(x);
+        drop(weight);
+        for (grad, value) in inputs[0].borrow_mut().grad.iter_mut().zip(x_grad) {
+            *grad += value;
+        }
+        for (grad, value) in inputs[1].borrow_mut().grad.iter_mut().zip(weight_grad) {
+            *grad += value;
+        }
+
+        // gradients for bias
+        if inputs.len() == 3 {
+            let mut bias = inputs[2].borrow_mut();
+            let out_channels = output.shape[1];
+            for (i, grad) in output.grad.iter().enumerate() {
+                bias.grad[(i / output_pixels) % out_channels] += grad;
+            }
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "Conv2d"
+    }
+}
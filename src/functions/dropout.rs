@@ -0,0 +1,80 @@
+use rand::Rng;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::function::FunctionImpl;
+use crate::random;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct Dropout {
+    pub p: f32,
+    // scaled mask sampled in forward and reused in backward
+    pub mask: Vec<f32>,
+}
+
+impl Dropout {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert!((0.0..=1.0).contains(&self.p));
+        assert_eq!(x.shape, output.shape);
+    }
+}
+
+impl FunctionImpl for Dropout {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        // inverted dropout keeps the expectation unchanged
+        let scale = if self.p < 1.0 {
+            1.0 / (1.0 - self.p)
+        } else {
+            0.0
+        };
+        self.mask = random::with_rng(|rng| {
+            (0..x.size())
+                .map(|_| {
+                    if rng.gen::<f32>() < self.p {
+                        0.0
+                    } else {
+                        scale
+                    }
+                })
+                .collect()
+        });
+        for i in 0..x.size() {
+            output.data[i] = x.data[i] * self.mask[i];
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        for i in 0..x.size() {
+            x.grad[i] += output.grad[i] * self.mask[i];
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "Dropout"
+    }
+}
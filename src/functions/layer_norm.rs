@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+// normalizes contiguous blocks of `size` trailing elements, followed by an
+// optional elementwise scale and shift
+//
+// inputs are x, then weight and bias when present
+#[derive(Debug)]
+pub struct LayerNorm {
+    pub size: usize,
+    pub eps: f32,
+    pub has_weight: bool,
+    pub has_bias: bool,
+}
+
+impl LayerNorm {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        let num_inputs = 1 + self.has_weight as usize + self.has_bias as usize;
+        assert_eq!(inputs.len(), num_inputs);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(x.shape, output.shape);
+        assert_eq!(x.size() % self.size, 0);
+        for input in inputs[1..].iter() {
+            assert_eq!(input.borrow().size(), self.size);
+        }
+    }
+
+    // normalized values and inverse standard deviations of each block
+    fn normalize(&self, x: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let mut x_hat = vec![0.0; x.len()];
+        let mut inv_stds = vec![0.0; x.len() / self.size];
+        for (i, inv_std) in inv_stds.iter_mut().enumerate() {
+            let block = &x[i * self.size..(i + 1) * self.size];
+            let mean = block.iter().sum::<f32>() / self.size as f32;
+            let var = block.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / self.size as f32;
+            *inv_std = 1.0 / (var + self.eps).sqrt();
+            for (j, v) in block.iter().enumerate() {
+                x_hat[i * self.size + j] = (v - mean) * *inv_std;
+            }
+        }
+        (x_hat, inv_stds)
+    }
+}
+
+impl FunctionImpl for LayerNorm {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let (x_hat, _) = self.normalize(&x.data);
+        output.data.copy_from_slice(&x_hat);
+        if self.has_weight {
+            let w = inputs[1].borrow();
+            for (i, value) in output.data.iter_mut().enumerate() {
+                *value *= w.data[i % self.size];
+            }
+        }
+        if self.has_bias {
+            let b = inputs[inputs.len() - 1].borrow();
+            for (i, value) in output.data.iter_mut().enumerate() {
+                *value += b.data[i % self.size];
+            }
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        // compute gradients first since inputs can be the same variable
+        let mut grads = vec![];
+        {
+            let x = inputs[0].borrow();
+            let output = outputs[0].borrow();
+            let (x_hat, inv_stds) = self.normalize(&x.data);
+
+            // gradients flowing into the normalized values
+            let mut g_hat = output.grad.clone();
+            let mut w_grad = vec![0.0; self.size];
+            if self.has_weight {
+                let w = inputs[1].borrow();
+                for (i, g) in g_hat.iter_mut().enumerate() {
+                    w_grad[i % self.size] += *g * x_hat[i];
+                    *g *= w.data[i % self.size];
+                }
+            }
+
+            // dx = (g_hat - mean(g_hat) - x_hat * mean(g_hat * x_hat)) / std
+            let mut x_grad = vec![0.0; x.size()];
+            let n = self.size as f32;
+            for (i, inv_std) in inv_stds.iter().enumerate() {
+                let range = i * self.size..(i + 1) * self.size;
+                let g_mean = g_hat[range.clone()].iter().sum::<f32>() / n;
+                let gx_mean = g_hat[range.clone()]
+                    .iter()
+                    .zip(x_hat[range.clone()].iter())
+                    .map(|(g, v)| g * v)
+                    .sum::<f32>()
+                    / n;
+                for j in range {
+                    x_grad[j] = (g_hat[j] - g_mean - x_hat[j] * gx_mean) * inv_std;
+                }
+            }
+            grads.push(x_grad);
+
+            if self.has_weight {
+                grads.push(w_grad);
+            }
+            if self.has_bias {
+                let mut b_grad = vec![0.0; self.size];
+                for (i, g) in output.grad.iter().enumerate() {
+                    b_grad[i % self.size] += g;
+                }
+                grads.push(b_grad);
+            }
+        }
+
+        for (input, grad) in inputs.iter().zip(grads) {
+            for (g, v) in input.borrow_mut().grad.iter_mut().zip(grad) {
+                *g += v;
+            }
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "LayerNorm"
+    }
+}
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::reduce::{reduce_indices, reduce_shape};
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct LogSumExp {
+    pub axes: Vec<usize>,
+    pub keepdims: bool,
+}
+
+impl LogSumExp {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(
+            reduce_shape(&x.shape, &self.axes, self.keepdims),
+            output.shape
+        );
+    }
+}
+
+impl FunctionImpl for LogSumExp {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let indices = reduce_indices(&x.shape, &self.axes);
+
+        // subtract maximum for numerical stability
+        let mut max = vec![f32::NEG_INFINITY; output.size()];
+        for (i, index) in indices.iter().enumerate() {
+            if x.data[i] > max[*index] {
+                max[*index] = x.data[i];
+            }
+        }
+        let mut sum = vec![0.0; output.size()];
+        for (i, index) in indices.iter().enumerate() {
+            sum[*index] += (x.data[i] - max[*index]).exp();
+        }
+        for (i, value) in output.data.iter_mut().enumerate() {
+            *value = max[i] + sum[i].ln();
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        let indices = reduce_indices(&x.shape, &self.axes);
+        for (i, index) in indices.iter().enumerate() {
+            let softmax = (x.data[i] - output.data[*index]).exp();
+            x.grad[i] += output.grad[*index] * softmax;
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "LogSumExp"
+    }
+}
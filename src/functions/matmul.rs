@@ -26,7 +26,7 @@ impl MatMul {
     }
 }
 
-fn transpose(x: &[f32], y: &mut [f32], shape: &[usize]) {
+pub fn transpose(x: &[f32], y: &mut [f32], shape: &[usize]) {
     for (i, v) in x.iter().enumerate().take(x.len()) {
         let orig_rows = i / shape[1];
         let orig_cols = i % shape[1];
@@ -35,7 +35,7 @@ fn transpose(x: &[f32], y: &mut [f32], shape: &[usize]) {
     }
 }
 
-fn matmul_impl(x: &[f32], x_shape: &[usize], y: &[f32], y_shape: &[usize], output: &mut [f32]) {
+pub fn matmul_impl(x: &[f32], x_shape: &[usize], y: &[f32], y_shape: &[usize], output: &mut [f32]) {
     let x_rows = x_shape[0];
     let x_cols = x_shape[1];
     let y_cols = y_shape[1];
@@ -77,33 +77,46 @@ impl FunctionImpl for MatMul {
     ) {
         self.validate(inputs, outputs);
 
-        let mut x = inputs[0].borrow_mut();
-        let mut y = inputs[1].borrow_mut();
-        let output = outputs[0].borrow();
-
-        // gradients for x
-        // g_out @ g_y^T = g_x
-        let mut transposed_y = vec![0.0; y.data.len()];
-        transpose(&y.data, &mut transposed_y, &y.shape);
-        matmul_impl(
-            &output.grad,
-            &output.shape,
-            &transposed_y,
-            &[y.shape[1], y.shape[0]],
-            &mut x.grad,
-        );
-
-        // gradients for y
-        // g_x^T @ g_out = g_y
-        let mut transposed_x = vec![0.0; x.data.len()];
-        transpose(&x.data, &mut transposed_x, &x.shape);
-        matmul_impl(
-            &transposed_x,
-            &[x.shape[1], x.shape[0]],
-            &output.grad,
-            &output.shape,
-            &mut y.grad,
-        );
+        // compute gradients first since x and y can be the same variable
+        let (x_grad, y_grad) = {
+            let x = inputs[0].borrow();
+            let y = inputs[1].borrow();
+            let output = outputs[0].borrow();
+
+            // gradients for x
+            // g_out @ g_y^T = g_x
+            let mut x_grad = vec![0.0; x.data.len()];
+            let mut transposed_y = vec![0.0; y.data.len()];
+            transpose(&y.data, &mut transposed_y, &y.shape);
+            matmul_impl(
+                &output.grad,
+                &output.shape,
+                &transposed_y,
+                &[y.shape[1], y.shape[0]],
+                &mut x_grad,
+            );
+
+            // gradients for y
+            // g_x^T @ g_out = g_y
+            let mut y_grad = vec![0.0; y.data.len()];
+            let mut transposed_x = vec![0.0; x.data.len()];
+            transpose(&x.data, &mut transposed_x, &x.shape);
+            matmul_impl(
+                &transposed_x,
+                &[x.shape[1], x.shape[0]],
+                &output.grad,
+                &output.shape,
+                &mut y_grad,
+            );
+            (x_grad, y_grad)
+        };
+
+        for (g, v) in inputs[0].borrow_mut().grad.iter_mut().zip(x_grad) {
+            *g += v;
+        }
+        for (g, v) in inputs[1].borrow_mut().grad.iter_mut().zip(y_grad) {
+            *g += v;
+        }
     }
 
     fn get_name(&self) -> &str {
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct MaxPool2d {
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    // input index of the maximum for each output element
+    pub indices: Vec<usize>,
+}
+
+impl MaxPool2d {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        // supports only NCHW layout
+        assert_eq!(x.shape.len(), 4);
+        assert_eq!(output.shape, self.output_shape(&x.shape));
+    }
+
+    pub fn output_shape(&self, x_shape: &[usize]) -> Vec<usize> {
+        assert!(self.stride.0 > 0 && self.stride.1 > 0);
+        let padded_height = x_shape[2] + 2 * self.padding.0;
+        let padded_width = x_shape[3] + 2 * self.padding.1;
+        assert!(padded_height >= self.kernel_size.0 && padded_width >= self.kernel_size.1);
+        assert!(self.padding.0 < self.kernel_size.0 && self.padding.1 < self.kernel_size.1);
+        vec![
+            x_shape[0],
+            x_shape[1],
+            (padded_height - self.kernel_size.0) / self.stride.0 + 1,
+            (padded_width - self.kernel_size.1) / self.stride.1 + 1,
+        ]
+    }
+}
+
+impl FunctionImpl for MaxPool2d {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let (height, width) = (x.shape[2] as isize, x.shape[3] as isize);
+        let (output_height, output_width) = (output.shape[2], output.shape[3]);
+        let planes = x.shape[0] * x.shape[1];
+
+        // padded pixels never become the maximum
+        self.indices = vec![0; output.size()];
+        for p in 0..planes {
+            let plane_offset = p * (height * width) as usize;
+            for oh in 0..output_height {
+                for ow in 0..output_width {
+                    let out_index = (p * output_height + oh) * output_width + ow;
+                    let mut max = f32::NEG_INFINITY;
+                    let mut max_index = None;
+                    for kh in 0..self.kernel_size.0 {
+                        for kw in 0..self.kernel_size.1 {
+                            let h = (oh * self.stride.0 + kh) as isize - self.padding.0 as isize;
+                            let w = (ow * self.stride.1 + kw) as isize - self.padding.1 as isize;
+                            if h < 0 || h >= height || w < 0 || w >= width {
+                                continue;
+                            }
+                            let index = plane_offset + (h * width + w) as usize;
+                            if max_index.is_none() || x.data[index] > max {
+                                max = x.data[index];
+                                max_index = Some(index);
+                            }
+                        }
+                    }
+                    output.data[out_index] = max;
+                    self.indices[out_index] = max_index.unwrap();
+                }
+            }
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        // route gradients to the maximums
+        for (i, index) in self.indices.iter().enumerate() {
+            x.grad[*index] += output.grad[i];
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "MaxPool2d"
+    }
+}
@@ -1,19 +1,28 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use super::reduce::{reduce_indices, reduce_shape};
 use crate::function::FunctionImpl;
 use crate::variable::Variable;
 
 #[derive(Debug)]
-pub struct Mean {}
+pub struct Mean {
+    pub axes: Vec<usize>,
+    pub keepdims: bool,
+}
 
 impl Mean {
     fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
         assert_eq!(inputs.len(), 1);
         assert_eq!(outputs.len(), 1);
 
+        let x = inputs[0].borrow();
         let output = outputs[0].borrow();
-        assert_eq!(output.size(), 1);
+
+        assert_eq!(
+            reduce_shape(&x.shape, &self.axes, self.keepdims),
+            output.shape
+        );
     }
 }
 
@@ -28,11 +37,15 @@ impl FunctionImpl for Mean {
         let x = inputs[0].borrow();
         let mut output = outputs[0].borrow_mut();
 
-        let mut sum = 0.0;
-        for i in 0..x.size() {
-            sum += x.data[i];
+        output.zeros();
+        let count = (x.size() / output.size()) as f32;
+        let indices = reduce_indices(&x.shape, &self.axes);
+        for (i, index) in indices.iter().enumerate() {
+            output.data[*index] += x.data[i];
+        }
+        for value in output.data.iter_mut() {
+            *value /= count;
         }
-        output.data[0] = sum / x.size() as f32;
     }
 
     fn backward_impl(
@@ -45,9 +58,10 @@ impl FunctionImpl for Mean {
         let mut x = inputs[0].borrow_mut();
         let output = outputs[0].borrow();
 
-        let total_size = x.size();
-        for i in 0..total_size {
-            x.grad[i] += output.grad[0] / total_size as f32;
+        let count = (x.size() / output.size()) as f32;
+        let indices = reduce_indices(&x.shape, &self.axes);
+        for (i, index) in indices.iter().enumerate() {
+            x.grad[i] += output.grad[*index] / count;
         }
     }
 
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::reduce::{reduce_indices, reduce_shape};
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct Min {
+    pub axes: Vec<usize>,
+    pub keepdims: bool,
+}
+
+impl Min {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(
+            reduce_shape(&x.shape, &self.axes, self.keepdims),
+            output.shape
+        );
+    }
+}
+
+impl FunctionImpl for Min {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        output.data.fill(f32::INFINITY);
+        let indices = reduce_indices(&x.shape, &self.axes);
+        for (i, index) in indices.iter().enumerate() {
+            if x.data[i] < output.data[*index] {
+                output.data[*index] = x.data[i];
+            }
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        let indices = reduce_indices(&x.shape, &self.axes);
+
+        // gradients are evenly distributed among tied minimums
+        let mut counts = vec![0.0; output.size()];
+        for (i, index) in indices.iter().enumerate() {
+            if x.data[i] == output.data[*index] {
+                counts[*index] += 1.0;
+            }
+        }
+        for (i, index) in indices.iter().enumerate() {
+            if x.data[i] == output.data[*index] {
+                x.grad[i] += output.grad[*index] / counts[*index];
+            }
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "Min"
+    }
+}
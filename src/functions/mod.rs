@@ -1,51 +1,100 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::function::CgFunction;
+use crate::function::{CgFunction, FunctionImpl};
+use crate::graph::is_grad_enabled;
 use crate::variable::Variable;
 
 mod add;
 mod argmax;
+mod avg_pool2d;
 mod broadcast;
+mod conv2d;
 mod div;
+mod dropout;
+mod layer_norm;
 mod log;
 mod log_softmax;
+mod logsumexp;
 mod matmul;
+mod max;
+mod max_pool2d;
 mod mean;
+mod min;
 mod mul;
 mod neg;
 mod onehot;
+mod prod;
+mod reduce;
 mod relu;
+mod reshape;
+mod rms_norm;
 mod softmax;
+mod sqrt;
 mod square;
+mod std_dev;
 mod sub;
+mod sum;
 
 use add::Add;
 use argmax::Argmax;
-use broadcast::Broadcast;
+use avg_pool2d::AvgPool2d;
+use broadcast::{broadcast_shape, Broadcast};
+use conv2d::Conv2d;
 use div::Div;
+use dropout::Dropout;
+use layer_norm::LayerNorm;
 use log::Log;
 use log_softmax::LogSoftmax;
+use logsumexp::LogSumExp;
 use matmul::MatMul;
+use max::Max;
+use max_pool2d::MaxPool2d;
 use mean::Mean;
+use min::Min;
 use mul::Mul;
 use neg::Neg;
 use onehot::Onehot;
+use prod::Prod;
+use reduce::{reduce_axes, reduce_shape};
 use relu::ReLu;
+use reshape::Reshape;
+use rms_norm::RmsNorm;
 use softmax::Softmax;
+use sqrt::Sqrt;
 use square::Square;
+use std_dev::StdDev;
 use sub::Sub;
+use sum::Sum;
+
+// runs the function and links it to the output as its parent, or only runs
+// the forward computation without building the graph when gradients are
+// disabled
+fn apply(
+    inputs: Vec<Rc<RefCell<Variable>>>,
+    output: &Rc<RefCell<Variable>>,
+    mut function: Box<dyn FunctionImpl>,
+) {
+    if !is_grad_enabled() {
+        function.forward_impl(&inputs, std::slice::from_ref(output));
+        output.borrow_mut().set_need_grad(false);
+        return;
+    }
 
-pub fn add(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
-    let function = Box::new(Add {});
     let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x, y],
+        inputs,
         vec![output.clone()],
         function,
     )));
     cg_function.borrow_mut().forward();
     output.borrow_mut().set_parent(cg_function);
+}
+
+pub fn add(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+    let shape = broadcast_shape(&x.borrow().shape, &y.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Add {});
+    apply(vec![x, y], &output, function);
     output
 }
 
@@ -53,66 +102,103 @@ pub fn argmax(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let shape = vec![x.borrow().shape[0]];
     let output = Rc::new(RefCell::new(Variable::new(shape)));
     let function = Box::new(Argmax {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output.borrow_mut().set_need_grad(false);
     output
 }
 
+pub fn avg_pool2d(
+    x: Rc<RefCell<Variable>>,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Rc<RefCell<Variable>> {
+    let function = Box::new(AvgPool2d {
+        kernel_size,
+        stride,
+        padding,
+    });
+    let shape = function.output_shape(&x.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    apply(vec![x], &output, function);
+    output
+}
+
 pub fn broadcast(x: Rc<RefCell<Variable>>, shape: Vec<usize>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(shape.clone())));
     let function = Box::new(Broadcast { shape });
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn conv2d(
+    x: Rc<RefCell<Variable>>,
+    weight: Rc<RefCell<Variable>>,
+    bias: Option<Rc<RefCell<Variable>>>,
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+    groups: usize,
+) -> Rc<RefCell<Variable>> {
+    let function = Box::new(Conv2d {
+        stride,
+        padding,
+        dilation,
+        groups,
+    });
+    let shape = function.output_shape(&x.borrow().shape, &weight.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let mut inputs = vec![x, weight];
+    if let Some(b) = bias {
+        inputs.push(b);
+    }
+    apply(inputs, &output, function);
     output
 }
 
 pub fn div(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let shape = broadcast_shape(&x.borrow().shape, &y.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
     let function = Box::new(Div {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x, y],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x, y], &output, function);
+    output
+}
+
+// zeroes elements with probability p in training and is the identity otherwise
+pub fn dropout(x: Rc<RefCell<Variable>>, p: f32, training: bool) -> Rc<RefCell<Variable>> {
+    if !training {
+        return x;
+    }
+    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let function = Box::new(Dropout { p, mask: vec![] });
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn log(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(Log {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn log_softmax(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(LogSoftmax {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn logsumexp(
+    x: Rc<RefCell<Variable>>,
+    axes: &[usize],
+    keepdims: bool,
+) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(LogSumExp { axes, keepdims });
+    apply(vec![x], &output, function);
     output
 }
 
@@ -122,52 +208,67 @@ pub fn matmul(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<
         y.borrow().shape[1],
     ])));
     let function = Box::new(MatMul {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x, y],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x, y], &output, function);
     output
 }
 
-pub fn mean(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-    let output = Rc::new(RefCell::new(Variable::new(vec![1])));
-    let function = Box::new(Mean {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+pub fn max(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Max { axes, keepdims });
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn max_pool2d(
+    x: Rc<RefCell<Variable>>,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Rc<RefCell<Variable>> {
+    let function = Box::new(MaxPool2d {
+        kernel_size,
+        stride,
+        padding,
+        indices: vec![],
+    });
+    let shape = function.output_shape(&x.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn mean(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Mean { axes, keepdims });
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn min(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Min { axes, keepdims });
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn mul(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let shape = broadcast_shape(&x.borrow().shape, &y.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
     let function = Box::new(Mul {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x, y],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x, y], &output, function);
     output
 }
 
 pub fn neg(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(Neg {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output
 }
 
@@ -175,66 +276,69 @@ pub fn onehot(x: Rc<RefCell<Variable>>, num_classes: u32) -> Rc<RefCell<Variable
     let shape = vec![x.borrow().shape[0], num_classes as usize];
     let output = Rc::new(RefCell::new(Variable::new(shape)));
     let function = Box::new(Onehot { num_classes });
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output.borrow_mut().set_need_grad(false);
     output
 }
 
+pub fn prod(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Prod { axes, keepdims });
+    apply(vec![x], &output, function);
+    output
+}
+
 pub fn relu(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(ReLu {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn reshape(x: Rc<RefCell<Variable>>, shape: Vec<usize>) -> Rc<RefCell<Variable>> {
+    let output = Rc::new(RefCell::new(Variable::new(shape.clone())));
+    let function = Box::new(Reshape { shape });
+    apply(vec![x], &output, function);
+    output
+}
+
+pub fn sqrt(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let function = Box::new(Sqrt {});
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn square(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(Square {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn softmax(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
     let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
     let function = Box::new(Softmax {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x], &output, function);
     output
 }
 
 pub fn sub(x: Rc<RefCell<Variable>>, y: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let shape = broadcast_shape(&x.borrow().shape, &y.borrow().shape);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
     let function = Box::new(Sub {});
-    let cg_function = Rc::new(RefCell::new(CgFunction::new(
-        vec![x, y],
-        vec![output.clone()],
-        function,
-    )));
-    cg_function.borrow_mut().forward();
-    output.borrow_mut().set_parent(cg_function);
+    apply(vec![x, y], &output, function);
+    output
+}
+
+pub fn sum(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(Sum { axes, keepdims });
+    apply(vec![x], &output, function);
     output
 }
 
@@ -243,13 +347,85 @@ pub fn cross_entropy_loss(
     t: Rc<RefCell<Variable>>,
 ) -> Rc<RefCell<Variable>> {
     assert_eq!(x.borrow().shape, t.borrow().shape);
-    mean(neg(mul(t, log_softmax(x))))
+    mean(neg(mul(t, log_softmax(x))), &[], false)
+}
+
+// average over spatial dimensions of NCHW tensors
+pub fn global_avg_pool2d(x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+    assert_eq!(x.borrow().shape.len(), 4);
+    mean(x, &[2, 3], true)
+}
+
+// normalizes over the trailing dimensions given by normalized_shape
+pub fn layer_norm(
+    x: Rc<RefCell<Variable>>,
+    normalized_shape: &[usize],
+    weight: Option<Rc<RefCell<Variable>>>,
+    bias: Option<Rc<RefCell<Variable>>>,
+    eps: f32,
+) -> Rc<RefCell<Variable>> {
+    let size = normalized_size(&x.borrow().shape, normalized_shape);
+    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let function = Box::new(LayerNorm {
+        size,
+        eps,
+        has_weight: weight.is_some(),
+        has_bias: bias.is_some(),
+    });
+    let inputs = std::iter::once(x).chain(weight).chain(bias).collect();
+    apply(inputs, &output, function);
+    output
+}
+
+// scales by the root mean square over the trailing dimensions without centering
+pub fn rms_norm(
+    x: Rc<RefCell<Variable>>,
+    normalized_shape: &[usize],
+    weight: Option<Rc<RefCell<Variable>>>,
+    eps: f32,
+) -> Rc<RefCell<Variable>> {
+    let size = normalized_size(&x.borrow().shape, normalized_shape);
+    let output = Rc::new(RefCell::new(Variable::new(x.borrow().shape.clone())));
+    let function = Box::new(RmsNorm {
+        size,
+        eps,
+        has_weight: weight.is_some(),
+    });
+    let inputs = std::iter::once(x).chain(weight).collect();
+    apply(inputs, &output, function);
+    output
+}
+
+// number of elements in each normalized block
+fn normalized_size(shape: &[usize], normalized_shape: &[usize]) -> usize {
+    assert!(!normalized_shape.is_empty());
+    assert!(normalized_shape.len() <= shape.len());
+    let offset = shape.len() - normalized_shape.len();
+    assert_eq!(&shape[offset..], normalized_shape);
+    normalized_shape.iter().product()
+}
+
+// population variance
+pub fn var(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let centered = sub(x.clone(), mean(x, axes, true));
+    mean(square(centered), axes, keepdims)
+}
+
+// population standard deviation with zero gradients for constant inputs
+pub fn std(x: Rc<RefCell<Variable>>, axes: &[usize], keepdims: bool) -> Rc<RefCell<Variable>> {
+    let axes = reduce_axes(&x.borrow().shape, axes);
+    let shape = reduce_shape(&x.borrow().shape, &axes, keepdims);
+    let output = Rc::new(RefCell::new(Variable::new(shape)));
+    let function = Box::new(StdDev { axes, keepdims });
+    apply(vec![x], &output, function);
+    output
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::graph::backward;
+    use rand::seq::SliceRandom;
     use rand::Rng;
 
     fn assert_eq_close(x: f32, y: f32, atol: f32) {
@@ -258,6 +434,47 @@ mod tests {
         }
     }
 
+    // compares gradients of sum(f(inputs) * coefficients) with finite differences
+    fn check_gradients<T>(f: T, inputs: &[Rc<RefCell<Variable>>], atol: f32)
+    where
+        T: Fn(Vec<Rc<RefCell<Variable>>>) -> Rc<RefCell<Variable>>,
+    {
+        let output = f(inputs.to_vec());
+        let coefficients = Variable::rand(output.borrow().shape.clone()).data;
+        let objective = |output: &Rc<RefCell<Variable>>| -> f64 {
+            let output = output.borrow();
+            let mut sum = 0.0;
+            for (value, coefficient) in output.data.iter().zip(coefficients.iter()) {
+                sum += (*value as f64) * (*coefficient as f64);
+            }
+            sum
+        };
+
+        for input in inputs.iter() {
+            input.borrow_mut().zero_grads();
+        }
+        let weights = Rc::new(RefCell::new(Variable::new(output.borrow().shape.clone())));
+        weights.borrow_mut().set_data(&coefficients);
+        weights.borrow_mut().set_need_grad(false);
+        backward(sum(mul(output, weights), &[], false));
+
+        let eps = 1e-2;
+        for input in inputs.iter() {
+            let size = input.borrow().size();
+            for i in 0..size {
+                let original = input.borrow().data[i];
+                input.borrow_mut().data[i] = original + eps;
+                let plus = objective(&f(inputs.to_vec()));
+                input.borrow_mut().data[i] = original - eps;
+                let minus = objective(&f(inputs.to_vec()));
+                input.borrow_mut().data[i] = original;
+
+                let numerical_grad = ((plus - minus) / (2.0 * eps as f64)) as f32;
+                assert_eq_close(input.borrow().grad[i], numerical_grad, atol);
+            }
+        }
+    }
+
     #[test]
     fn add_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
@@ -282,7 +499,7 @@ mod tests {
 
         let x_data = &x.borrow().data;
         let output_data = &output.borrow().data;
-        for i in 0..x.borrow().shape[0] {
+        for (i, output_value) in output_data.iter().enumerate() {
             let offset = i * x.borrow().shape[1];
             let mut max = x_data[offset];
             let mut max_index = 0;
@@ -292,10 +509,41 @@ mod tests {
                     max_index = j;
                 }
             }
-            assert_eq!(output_data[i] as usize, max_index);
+            assert_eq!(*output_value as usize, max_index);
         }
     }
 
+    #[test]
+    fn avg_pool2d_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 4, 4])));
+        let data: Vec<f32> = (0..16).map(|v| v as f32).collect();
+        x.borrow_mut().set_data(&data);
+
+        let output = avg_pool2d(x.clone(), (2, 2), (2, 2), (0, 0));
+        assert_eq!(output.borrow().shape, vec![1, 1, 2, 2]);
+        assert_eq!(output.borrow().data, vec![2.5, 4.5, 10.5, 12.5]);
+
+        let output = avg_pool2d(x, (3, 3), (2, 2), (1, 1));
+        assert_eq!(output.borrow().shape, vec![1, 1, 2, 2]);
+        assert_eq!(output.borrow().data[0], 10.0 / 9.0);
+    }
+
+    #[test]
+    fn avg_pool2d_backward() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 3, 5, 5])));
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            avg_pool2d(inputs[0].clone(), (3, 2), (2, 1), (1, 1))
+        };
+        check_gradients(f, &[x], 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn avg_pool2d_zero_stride() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 4, 4])));
+        avg_pool2d(x, (2, 2), (0, 1), (0, 0));
+    }
+
     #[test]
     fn broadcast_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
@@ -312,6 +560,148 @@ mod tests {
         }
     }
 
+    #[test]
+    fn broadcast_inner_dimensions() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3, 1])));
+        x.borrow_mut().set_data(&[1.0, 2.0, 3.0]);
+        let output = broadcast(x.clone(), vec![2, 3, 2]);
+        backward(output.clone());
+
+        assert_eq!(
+            output.borrow().data,
+            vec![1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0]
+        );
+        assert_eq!(x.borrow().grad, vec![4.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn add_broadcast_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 1, 3])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![2, 1])));
+        x.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        y.borrow_mut().set_data(&[10.0, 20.0]);
+        let output = add(x.clone(), y.clone());
+        backward(output.clone());
+
+        assert_eq!(output.borrow().shape, vec![2, 2, 3]);
+        assert_eq!(
+            output.borrow().data,
+            vec![11.0, 12.0, 13.0, 21.0, 22.0, 23.0, 14.0, 15.0, 16.0, 24.0, 25.0, 26.0]
+        );
+
+        // gradients are reduced back to the original shapes
+        assert_eq!(x.borrow().grad, vec![2.0; 6]);
+        assert_eq!(y.borrow().grad, vec![6.0, 6.0]);
+    }
+
+    #[test]
+    fn mul_broadcast_backward() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0]);
+        y.borrow_mut().set_data(&[5.0, 6.0]);
+        let output = mul(x.clone(), y.clone());
+        backward(output.clone());
+
+        assert_eq!(output.borrow().data, vec![5.0, 12.0, 15.0, 24.0]);
+        assert_eq!(x.borrow().grad, vec![5.0, 6.0, 5.0, 6.0]);
+        assert_eq!(y.borrow().grad, vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn mul_same_variable() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3])));
+        x.borrow_mut().set_data(&[1.0, -2.0, 3.0]);
+        let output = mul(x.clone(), x.clone());
+        backward(output);
+
+        assert_eq!(x.borrow().grad, vec![2.0, -4.0, 6.0]);
+    }
+
+    #[test]
+    fn div_backward() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![1])));
+        x.borrow_mut().set_data(&[1.0, 4.0]);
+        y.borrow_mut().set_data(&[2.0]);
+        let output = div(x.clone(), y.clone());
+        backward(output);
+
+        assert_eq!(x.borrow().grad, vec![0.5, 0.5]);
+        assert_eq!(y.borrow().grad, vec![-1.25]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_incompatible_shapes() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![2])));
+        add(x, y);
+    }
+
+    #[test]
+    fn conv2d_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 3, 3])));
+        let w = Rc::new(RefCell::new(Variable::new(vec![1, 1, 2, 2])));
+        let b = Rc::new(RefCell::new(Variable::new(vec![1])));
+        x.borrow_mut()
+            .set_data(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        w.borrow_mut().set_data(&[1.0, 0.0, 0.0, -1.0]);
+        b.borrow_mut().set_data(&[0.5]);
+
+        let output = conv2d(x.clone(), w.clone(), Some(b), (1, 1), (0, 0), (1, 1), 1);
+        assert_eq!(output.borrow().shape, vec![1, 1, 2, 2]);
+        assert_eq!(output.borrow().data, vec![-3.5, -3.5, -3.5, -3.5]);
+
+        let output = conv2d(x, w, None, (2, 2), (1, 1), (1, 1), 1);
+        assert_eq!(output.borrow().shape, vec![1, 1, 2, 2]);
+        assert_eq!(output.borrow().data, vec![-1.0, -3.0, -7.0, -4.0]);
+    }
+
+    #[test]
+    fn conv2d_backward() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 4, 5, 5])));
+        let w = Rc::new(RefCell::new(Variable::rand(vec![6, 2, 3, 3])));
+        let b = Rc::new(RefCell::new(Variable::rand(vec![6])));
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            let bias = Some(inputs[2].clone());
+            conv2d(
+                inputs[0].clone(),
+                inputs[1].clone(),
+                bias,
+                (2, 1),
+                (1, 1),
+                (1, 2),
+                2,
+            )
+        };
+        assert_eq!(
+            f(vec![x.clone(), w.clone(), b.clone()]).borrow().shape,
+            vec![2, 6, 3, 3]
+        );
+        check_gradients(f, &[x, w, b], 0.01);
+    }
+
+    #[test]
+    fn conv2d_same_input_and_weight() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 2, 2])));
+        x.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0]);
+
+        // the output is the sum of squares, so gradients are doubled
+        let output = conv2d(x.clone(), x.clone(), None, (1, 1), (0, 0), (1, 1), 1);
+        assert_eq!(output.borrow().data, vec![30.0]);
+        backward(output);
+        assert_eq!(x.borrow().grad, vec![2.0, 4.0, 6.0, 8.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn conv2d_zero_stride() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 3, 3])));
+        let w = Rc::new(RefCell::new(Variable::new(vec![1, 1, 2, 2])));
+        conv2d(x, w, None, (1, 0), (0, 0), (1, 1), 1);
+    }
+
     #[test]
     fn div_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
@@ -327,6 +717,119 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dropout_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![100, 100])));
+        x.borrow_mut().ones();
+        let output = dropout(x.clone(), 0.3, true);
+        backward(output.clone());
+
+        // kept elements are scaled and gradients reuse the mask
+        let mut kept = 0;
+        for (y, g) in output.borrow().data.iter().zip(x.borrow().grad.iter()) {
+            assert_eq!(y, g);
+            if *y != 0.0 {
+                assert_eq_close(*y, 1.0 / 0.7, 0.0001);
+                kept += 1;
+            }
+        }
+        assert!((kept as f32 / 10000.0 - 0.7).abs() < 0.05);
+    }
+
+    #[test]
+    fn dropout_seed() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![10, 10])));
+        crate::random::seed(1);
+        let y1 = dropout(x.clone(), 0.5, true);
+        crate::random::seed(1);
+        let y2 = dropout(x, 0.5, true);
+        assert_eq!(y1.borrow().data, y2.borrow().data);
+    }
+
+    #[test]
+    fn dropout_eval() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![10, 10])));
+        let output = dropout(x.clone(), 0.5, false);
+        assert!(Rc::ptr_eq(&output, &x));
+        assert!(output.borrow().parent.is_none());
+    }
+
+    #[test]
+    fn layer_norm_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3, 2, 4])));
+        x.borrow_mut().set_data(&[
+            0.5, -1.0, 2.0, 0.0, 1.5, -0.5, 3.0, 1.0, -2.0, -1.0, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0,
+            10.0, 12.0, 9.0, 11.0, 8.0, 13.0, 10.5, 9.5,
+        ]);
+        let output = layer_norm(x, &[2, 4], None, None, 1e-5);
+        assert_eq!(output.borrow().shape, vec![3, 2, 4]);
+
+        // every sample has zero mean and unit variance
+        let y = &output.borrow().data;
+        for i in 0..3 {
+            let values = &y[i * 8..(i + 1) * 8];
+            let mean: f32 = values.iter().sum::<f32>() / 8.0;
+            let var: f32 = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / 8.0;
+            assert_eq_close(mean, 0.0, 0.0001);
+            assert_eq_close(var, 1.0, 0.001);
+        }
+    }
+
+    #[test]
+    fn layer_norm_backward() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3, 4])));
+        let w = Rc::new(RefCell::new(Variable::new(vec![4])));
+        let b = Rc::new(RefCell::new(Variable::new(vec![4])));
+        x.borrow_mut().set_data(&[
+            0.2, -0.7, 1.1, 0.4, -1.5, 0.3, 0.9, -0.2, 2.0, 1.2, -0.6, 0.8,
+        ]);
+        w.borrow_mut().set_data(&[0.5, 1.5, -1.0, 2.0]);
+        b.borrow_mut().set_data(&[0.1, -0.3, 0.0, 0.6]);
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            let weight = Some(inputs[1].clone());
+            let bias = Some(inputs[2].clone());
+            layer_norm(inputs[0].clone(), &[4], weight, bias, 1e-5)
+        };
+        check_gradients(f, &[x.clone(), w, b], 0.01);
+
+        // normalization and affine transform are a single function
+        let output = layer_norm(x.clone(), &[4], None, None, 1e-5);
+        let parent = output.borrow().parent.as_ref().unwrap().clone();
+        assert_eq!(parent.borrow().get_inputs().len(), 1);
+
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            layer_norm(inputs[0].clone(), &[4], None, None, 1e-5)
+        };
+        check_gradients(f, &[x], 0.01);
+    }
+
+    #[test]
+    fn rms_norm_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        x.borrow_mut().set_data(&[3.0, 4.0, 1.0, -1.0]);
+        let output = rms_norm(x, &[2], None, 0.0);
+
+        let scale = (12.5f32).sqrt();
+        let expected = [3.0 / scale, 4.0 / scale, 1.0, -1.0];
+        for (y, e) in output.borrow().data.iter().zip(expected.iter()) {
+            assert_eq_close(*y, *e, 0.0001);
+        }
+    }
+
+    #[test]
+    fn rms_norm_backward() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 3, 4])));
+        let w = Rc::new(RefCell::new(Variable::rand(vec![3, 4])));
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            let weight = Some(inputs[1].clone());
+            rms_norm(inputs[0].clone(), &[3, 4], weight, 1e-5)
+        };
+        check_gradients(f, &[x.clone(), w], 0.01);
+
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| rms_norm(inputs[0].clone(), &[4], None, 1e-5);
+        check_gradients(f, &[x], 0.01);
+    }
+
     #[test]
     fn log_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
@@ -380,21 +883,93 @@ mod tests {
         backward(output);
     }
 
+    #[test]
+    fn max_pool2d_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 4, 4])));
+        let data: Vec<f32> = (0..16).map(|v| v as f32).collect();
+        x.borrow_mut().set_data(&data);
+
+        let output = max_pool2d(x.clone(), (2, 2), (2, 2), (0, 0));
+        assert_eq!(output.borrow().shape, vec![1, 1, 2, 2]);
+        assert_eq!(output.borrow().data, vec![5.0, 7.0, 13.0, 15.0]);
+        backward(output);
+
+        // gradients are routed to the maximums
+        let mut grad = vec![0.0; 16];
+        for i in [5, 7, 13, 15] {
+            grad[i] = 1.0;
+        }
+        assert_eq!(x.borrow().grad, grad);
+
+        // padded pixels are ignored even when inputs are negative
+        x.borrow_mut().set_data(&[-1.0; 16]);
+        let output = max_pool2d(x, (3, 3), (2, 2), (1, 1));
+        assert_eq!(output.borrow().data, vec![-1.0; 4]);
+    }
+
+    #[test]
+    fn max_pool2d_backward() {
+        // distinct values to keep finite differences away from ties
+        crate::random::seed(0);
+        let mut data: Vec<f32> = (0..150).map(|v| v as f32 * 0.1).collect();
+        crate::random::with_rng(|rng| data.shuffle(rng));
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3, 5, 5])));
+        x.borrow_mut().set_data(&data);
+        let f = |inputs: Vec<Rc<RefCell<Variable>>>| {
+            max_pool2d(inputs[0].clone(), (2, 3), (1, 2), (1, 1))
+        };
+        check_gradients(f, &[x], 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn max_pool2d_zero_stride() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 1, 4, 4])));
+        max_pool2d(x, (2, 2), (0, 1), (0, 0));
+    }
+
+    #[test]
+    fn global_avg_pool2d_variables() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 3, 4, 5])));
+        let output = global_avg_pool2d(x.clone());
+        backward(output.clone());
+
+        assert_eq!(output.borrow().shape, vec![2, 3, 1, 1]);
+        for i in 0..6 {
+            let sum: f32 = x.borrow().data[i * 20..(i + 1) * 20].iter().sum();
+            assert_eq_close(output.borrow().data[i], sum / 20.0, 0.0001);
+        }
+        assert_eq!(x.borrow().grad, vec![0.05; 120]);
+    }
+
     #[test]
     fn mean_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
-        let output = mean(x.clone());
+        let output = mean(x.clone(), &[], false);
         backward(output.clone());
 
         let x_data = &x.borrow().data;
         let output_data = &output.borrow().data;
-        let mut sum = 0.0;
-        for i in 0..x.borrow().size() {
-            sum += x_data[i];
-        }
+        let sum: f32 = x_data.iter().sum();
         assert_eq!(output_data[0], sum / x.borrow().size() as f32);
     }
 
+    #[test]
+    fn mean_axes_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        x.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let output = mean(x.clone(), &[1], false);
+        assert_eq!(output.borrow().shape, vec![2]);
+        assert_eq!(output.borrow().data, vec![2.0, 5.0]);
+
+        let output = mean(x.clone(), &[0], true);
+        assert_eq!(output.borrow().shape, vec![1, 3]);
+        assert_eq!(output.borrow().data, vec![2.5, 3.5, 4.5]);
+        backward(output);
+        assert_eq!(x.borrow().grad, vec![0.5; 6]);
+    }
+
     #[test]
     fn mul_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![1, 2, 3])));
@@ -426,21 +1001,22 @@ mod tests {
     #[test]
     fn onehot_variables() {
         let x = Rc::new(RefCell::new(Variable::new(vec![10])));
-        let mut rng = rand::thread_rng();
-        let size = x.borrow().size();
-        for i in 0..size {
-            x.borrow_mut().data[i] = rng.gen_range(0, 20) as f32;
-        }
+        crate::random::seed(0);
+        crate::random::with_rng(|rng| {
+            for value in x.borrow_mut().data.iter_mut() {
+                *value = rng.gen_range(0, 20) as f32;
+            }
+        });
         let output = onehot(x.clone(), 20);
         assert_eq!(output.borrow().shape[0], 10);
         assert_eq!(output.borrow().shape[1], 20);
 
         let x_data = &x.borrow().data;
         let output_data = &output.borrow().data;
-        for i in 0..x.borrow().size() {
+        for (i, x_value) in x_data.iter().enumerate() {
             let offset = i * 20;
-            for j in 0..20 as usize {
-                if x_data[i] == j as f32 {
+            for j in 0..20_usize {
+                if *x_value == j as f32 {
                     assert_eq!(output_data[j + offset], 1.0);
                 } else {
                     assert_eq!(output_data[j + offset], 0.0);
@@ -466,6 +1042,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reshape_variables() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 6])));
+        let output = reshape(x.clone(), vec![3, 2, 2]);
+        backward(output.clone());
+
+        assert_eq!(output.borrow().shape, vec![3, 2, 2]);
+        assert_eq!(output.borrow().data, x.borrow().data);
+        assert_eq!(x.borrow().grad, vec![1.0; 12]);
+    }
+
     #[test]
     fn softmax_variables() {
         let x = Rc::new(RefCell::new(Variable::rand(vec![2, 10])));
@@ -496,6 +1083,121 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sum_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 2, 2])));
+        x.borrow_mut()
+            .set_data(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+
+        let output = sum(x.clone(), &[0, 2], false);
+        assert_eq!(output.borrow().shape, vec![2]);
+        assert_eq!(output.borrow().data, vec![14.0, 22.0]);
+
+        let output = sum(x.clone(), &[], true);
+        assert_eq!(output.borrow().shape, vec![1, 1, 1]);
+        assert_eq!(output.borrow().data, vec![36.0]);
+        backward(output);
+        assert_eq!(x.borrow().grad, vec![1.0; 8]);
+    }
+
+    #[test]
+    fn max_min_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        x.borrow_mut().set_data(&[1.0, 3.0, 3.0, -1.0, -2.0, 0.0]);
+
+        let output = max(x.clone(), &[1], false);
+        assert_eq!(output.borrow().data, vec![3.0, 0.0]);
+        backward(output);
+
+        // ties share the gradient
+        assert_eq!(x.borrow().grad, vec![0.0, 0.5, 0.5, 0.0, 0.0, 1.0]);
+
+        x.borrow_mut().zero_grads();
+        let output = min(x.clone(), &[0], true);
+        assert_eq!(output.borrow().shape, vec![1, 3]);
+        assert_eq!(output.borrow().data, vec![-1.0, -2.0, 0.0]);
+        backward(output);
+        assert_eq!(x.borrow().grad, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn prod_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3, 2])));
+        x.borrow_mut().set_data(&[2.0, 0.0, 3.0, 0.0, 4.0, 5.0]);
+
+        let output = prod(x.clone(), &[0], false);
+        assert_eq!(output.borrow().data, vec![24.0, 0.0]);
+        backward(output);
+
+        // gradients are well-defined with zeros
+        assert_eq!(x.borrow().grad, vec![12.0, 0.0, 8.0, 0.0, 6.0, 0.0]);
+
+        let y = Rc::new(RefCell::new(Variable::new(vec![3])));
+        y.borrow_mut().set_data(&[2.0, 0.0, 3.0]);
+        let output = prod(y.clone(), &[], false);
+        backward(output);
+        assert_eq!(y.borrow().grad, vec![0.0, 6.0, 0.0]);
+    }
+
+    #[test]
+    fn var_std_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        x.borrow_mut().set_data(&[1.0, 3.0, 2.0, 6.0]);
+
+        let output = var(x.clone(), &[1], false);
+        assert_eq!(output.borrow().data, vec![1.0, 4.0]);
+        backward(output);
+
+        // d/dx var = 2 (x - mean) / n
+        assert_eq!(x.borrow().grad, vec![-1.0, 1.0, -2.0, 2.0]);
+
+        x.borrow_mut().zero_grads();
+        let output = std(x.clone(), &[1], true);
+        assert_eq!(output.borrow().shape, vec![2, 1]);
+        assert_eq!(output.borrow().data, vec![1.0, 2.0]);
+        backward(output);
+        assert_eq!(x.borrow().grad, vec![-0.5, 0.5, -0.5, 0.5]);
+
+        let x = Rc::new(RefCell::new(Variable::new(vec![3, 2])));
+        x.borrow_mut().set_data(&[0.5, -1.0, 2.0, 0.3, -0.7, 1.4]);
+        check_gradients(|inputs| std(inputs[0].clone(), &[0], false), &[x], 0.01);
+    }
+
+    #[test]
+    fn std_constant_input() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        x.borrow_mut().set_data(&[2.0, 2.0, 2.0, 1.0, 2.0, 3.0]);
+
+        let output = std(x.clone(), &[1], false);
+        assert_eq!(output.borrow().data[0], 0.0);
+        backward(output);
+
+        // zero variance has zero gradients instead of NaN
+        let grad = &x.borrow().grad;
+        assert_eq!(grad[..3], [0.0, 0.0, 0.0]);
+        assert!(grad.iter().all(|g| g.is_finite()));
+    }
+
+    #[test]
+    fn logsumexp_variables() {
+        let x = Rc::new(RefCell::new(Variable::rand(vec![4, 5])));
+        let output = logsumexp(x.clone(), &[1], false);
+        backward(output.clone());
+
+        let x_data = &x.borrow().data;
+        let x_grad = &x.borrow().grad;
+        for i in 0..4 {
+            let row = &x_data[i * 5..(i + 1) * 5];
+            let sum: f32 = row.iter().map(|v| v.exp()).sum();
+            assert_eq_close(output.borrow().data[i], sum.ln(), 0.0001);
+
+            // gradients are softmax
+            for j in 0..5 {
+                assert_eq_close(x_grad[i * 5 + j], row[j].exp() / sum, 0.0001);
+            }
+        }
+    }
+
     #[test]
     fn cross_entropy_loss_variables() {
         let x = Rc::new(RefCell::new(Variable::new(vec![32, 10])));
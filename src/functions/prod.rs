@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::reduce::{reduce_indices, reduce_shape};
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+#[derive(Debug)]
+pub struct Prod {
+    pub axes: Vec<usize>,
+    pub keepdims: bool,
+}
+
+impl Prod {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(
+            reduce_shape(&x.shape, &self.axes, self.keepdims),
+            output.shape
+        );
+    }
+}
+
+impl FunctionImpl for Prod {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        output.ones();
+        let indices = reduce_indices(&x.shape, &self.axes);
+        for (i, index) in indices.iter().enumerate() {
+            output.data[*index] *= x.data[i];
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        let indices = reduce_indices(&x.shape, &self.axes);
+
+        // products of non-zero elements and number of zeros to avoid division by zero
+        let mut nonzero_prods = vec![1.0; output.size()];
+        let mut zero_counts = vec![0; output.size()];
+        for (i, index) in indices.iter().enumerate() {
+            if x.data[i] == 0.0 {
+                zero_counts[*index] += 1;
+            } else {
+                nonzero_prods[*index] *= x.data[i];
+            }
+        }
+        for (i, index) in indices.iter().enumerate() {
+            let grad = match (zero_counts[*index], x.data[i] == 0.0) {
+                (0, _) => nonzero_prods[*index] / x.data[i],
+                (1, true) => nonzero_prods[*index],
+                _ => 0.0,
+            };
+            x.grad[i] += output.grad[*index] * grad;
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "Prod"
+    }
+}
@@ -0,0 +1,62 @@
+// helpers shared by the reduction functions
+//
+// an empty list of axes reduces all axes
+
+pub fn reduce_axes(shape: &[usize], axes: &[usize]) -> Vec<usize> {
+    if axes.is_empty() {
+        return (0..shape.len()).collect();
+    }
+    let mut axes = axes.to_vec();
+    axes.sort_unstable();
+    axes.dedup();
+    for axis in axes.iter() {
+        assert!(
+            *axis < shape.len(),
+            "axis {} is out of range for shape {:?}",
+            axis,
+            shape
+        );
+    }
+    axes
+}
+
+pub fn reduce_shape(shape: &[usize], axes: &[usize], keepdims: bool) -> Vec<usize> {
+    let mut reduced_shape = vec![];
+    for (i, dim_size) in shape.iter().enumerate() {
+        if !axes.contains(&i) {
+            reduced_shape.push(*dim_size);
+        } else if keepdims {
+            reduced_shape.push(1);
+        }
+    }
+
+    // scalars are represented as 1-element tensors
+    if reduced_shape.is_empty() {
+        reduced_shape.push(1);
+    }
+    reduced_shape
+}
+
+// maps each element of the input to the element of the reduced output
+pub fn reduce_indices(shape: &[usize], axes: &[usize]) -> Vec<usize> {
+    // reduced dimensions have zero strides
+    let mut strides = vec![0; shape.len()];
+    let mut stride = 1;
+    for i in (0..shape.len()).rev() {
+        if !axes.contains(&i) {
+            strides[i] = stride;
+            stride *= shape[i];
+        }
+    }
+
+    let size = shape.iter().product();
+    let mut indices = vec![0; size];
+    for (i, index) in indices.iter_mut().enumerate() {
+        let mut rest = i;
+        for d in (0..shape.len()).rev() {
+            *index += (rest % shape[d]) * strides[d];
+            rest /= shape[d];
+        }
+    }
+    indices
+}
@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+// scales contiguous blocks of `size` trailing elements by their inverse root
+// mean square, followed by an optional elementwise scale
+//
+// inputs are x, then weight when present
+#[derive(Debug)]
+pub struct RmsNorm {
+    pub size: usize,
+    pub eps: f32,
+    pub has_weight: bool,
+}
+
+impl RmsNorm {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1 + self.has_weight as usize);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(x.shape, output.shape);
+        assert_eq!(x.size() % self.size, 0);
+        if self.has_weight {
+            assert_eq!(inputs[1].borrow().size(), self.size);
+        }
+    }
+
+    // inverse root mean square of each block
+    fn inv_rms(&self, x: &[f32]) -> Vec<f32> {
+        x.chunks(self.size)
+            .map(|block| {
+                let mean_square = block.iter().map(|v| v * v).sum::<f32>() / self.size as f32;
+                1.0 / (mean_square + self.eps).sqrt()
+            })
+            .collect()
+    }
+}
+
+impl FunctionImpl for RmsNorm {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let inv_rms = self.inv_rms(&x.data);
+        for (i, value) in output.data.iter_mut().enumerate() {
+            *value = x.data[i] * inv_rms[i / self.size];
+        }
+        if self.has_weight {
+            let w = inputs[1].borrow();
+            for (i, value) in output.data.iter_mut().enumerate() {
+                *value *= w.data[i % self.size];
+            }
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        // compute gradients first since x and weight can be the same variable
+        let (x_grad, w_grad) = {
+            let x = inputs[0].borrow();
+            let output = outputs[0].borrow();
+            let inv_rms = self.inv_rms(&x.data);
+
+            // gradients flowing into the normalized values
+            let mut g_hat = output.grad.clone();
+            let mut w_grad = vec![0.0; self.size];
+            if self.has_weight {
+                let w = inputs[1].borrow();
+                for (i, g) in g_hat.iter_mut().enumerate() {
+                    w_grad[i % self.size] += *g * x.data[i] * inv_rms[i / self.size];
+                    *g *= w.data[i % self.size];
+                }
+            }
+
+            // dx = (g_hat - x_hat * mean(g_hat * x_hat)) / rms
+            let mut x_grad = vec![0.0; x.size()];
+            for (i, inv) in inv_rms.iter().enumerate() {
+                let range = i * self.size..(i + 1) * self.size;
+                let gx_mean = range
+                    .clone()
+                    .map(|j| g_hat[j] * x.data[j] * inv)
+                    .sum::<f32>()
+                    / self.size as f32;
+                for j in range {
+                    x_grad[j] = (g_hat[j] - x.data[j] * inv * gx_mean) * inv;
+                }
+            }
+            (x_grad, w_grad)
+        };
+
+        for (g, v) in inputs[0].borrow_mut().grad.iter_mut().zip(x_grad) {
+            *g += v;
+        }
+        if self.has_weight {
+            for (g, v) in inputs[1].borrow_mut().grad.iter_mut().zip(w_grad) {
+                *g += v;
+            }
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "RmsNorm"
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::reduce::{reduce_indices, reduce_shape};
+use crate::function::FunctionImpl;
+use crate::variable::Variable;
+
+// population standard deviation
+#[derive(Debug)]
+pub struct StdDev {
+    pub axes: Vec<usize>,
+    pub keepdims: bool,
+}
+
+impl StdDev {
+    fn validate(&mut self, inputs: &[Rc<RefCell<Variable>>], outputs: &[Rc<RefCell<Variable>>]) {
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(outputs.len(), 1);
+
+        let x = inputs[0].borrow();
+        let output = outputs[0].borrow();
+
+        assert_eq!(
+            reduce_shape(&x.shape, &self.axes, self.keepdims),
+            output.shape
+        );
+    }
+
+    fn means(&self, x: &Variable, indices: &[usize], size: usize) -> Vec<f32> {
+        let count = (x.size() / size) as f32;
+        let mut means = vec![0.0; size];
+        for (i, index) in indices.iter().enumerate() {
+            means[*index] += x.data[i];
+        }
+        for mean in means.iter_mut() {
+            *mean /= count;
+        }
+        means
+    }
+}
+
+impl FunctionImpl for StdDev {
+    fn forward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let x = inputs[0].borrow();
+        let mut output = outputs[0].borrow_mut();
+
+        let count = (x.size() / output.size()) as f32;
+        let indices = reduce_indices(&x.shape, &self.axes);
+        let means = self.means(&x, &indices, output.size());
+        output.zeros();
+        for (i, index) in indices.iter().enumerate() {
+            let centered = x.data[i] - means[*index];
+            output.data[*index] += centered * centered;
+        }
+        for value in output.data.iter_mut() {
+            *value = (*value / count).sqrt();
+        }
+    }
+
+    fn backward_impl(
+        &mut self,
+        inputs: &[Rc<RefCell<Variable>>],
+        outputs: &[Rc<RefCell<Variable>>],
+    ) {
+        self.validate(inputs, outputs);
+
+        let mut x = inputs[0].borrow_mut();
+        let output = outputs[0].borrow();
+
+        // d/dx std = (x - mean) / (n * std), which is taken as zero for
+        // constant inputs where std is zero and every x - mean is zero
+        let count = (x.size() / output.size()) as f32;
+        let indices = reduce_indices(&x.shape, &self.axes);
+        let means = self.means(&x, &indices, output.size());
+        for (i, index) in indices.iter().enumerate() {
+            let std = output.data[*index];
+            if std > 0.0 {
+                let centered = x.data[i] - means[*index];
+                x.grad[i] += output.grad[*index] * centered / (count * std);
+            }
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        "StdDev"
+    }
+}
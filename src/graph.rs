@@ -1,37 +1,453 @@
-use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use crate::function::CgFunction;
 use crate::variable::Variable;
 
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+// restores the previous mode when dropped
+pub struct NoGradGuard {
+    previous: bool,
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+// disables graph construction until the returned guard is dropped, so that
+// functions only compute outputs without retaining their inputs
+#[must_use]
+pub fn no_grad() -> NoGradGuard {
+    let previous = GRAD_ENABLED.with(|enabled| enabled.replace(false));
+    NoGradGuard { previous }
+}
+
+// returns functions ordered so that every function comes before the
+// functions producing its inputs
+fn topological_sort(roots: Vec<Rc<RefCell<CgFunction>>>) -> Vec<Rc<RefCell<CgFunction>>> {
+    let mut visited: HashSet<*const RefCell<CgFunction>> = HashSet::new();
+    let mut order: Vec<Rc<RefCell<CgFunction>>> = Vec::new();
+
+    // iterative depth-first search to avoid stack overflow on deep graphs
+    let mut stack: Vec<(Rc<RefCell<CgFunction>>, bool)> =
+        roots.into_iter().map(|root| (root, false)).collect();
+    while let Some((function, expanded)) = stack.pop() {
+        if expanded {
+            order.push(function);
+            continue;
+        }
+        if !visited.insert(Rc::as_ptr(&function)) {
+            continue;
+        }
+        stack.push((function.clone(), true));
+
+        for input in function.borrow().get_inputs().iter() {
+            let borrowed_input = input.borrow();
+            if !borrowed_input.need_grad {
+                continue;
+            }
+            if let Some(p) = borrowed_input.parent.as_ref() {
+                if !visited.contains(&Rc::as_ptr(p)) {
+                    stack.push((p.clone(), false));
+                }
+            }
+        }
+    }
+
+    // post-order puts producers first
+    order.reverse();
+    order
+}
+
+// frees the graph after computing gradients
 pub fn backward(variable: Rc<RefCell<Variable>>) {
+    backward_with(variable, false);
+}
+
+// keeps the graph for another backward if retain_graph is true, and panics
+// when the graph has already been freed
+pub fn backward_with(variable: Rc<RefCell<Variable>>, retain_graph: bool) {
     if variable.borrow().parent.is_none() {
         return;
     }
 
-    // initialize leaf gradient with ones
+    let root = variable.borrow().parent.as_ref().unwrap().clone();
+    let functions = topological_sort(vec![root]);
+    for function in functions.iter() {
+        function.borrow().check_not_freed();
+    }
+
+    // gradients of intermediate variables start from zero on every call
+    // so that retained graphs accumulate only into leaves
+    for function in functions.iter() {
+        for output in function.borrow().get_outputs().iter() {
+            output.borrow_mut().zero_grads();
+        }
+    }
+
+    // initialize root gradient with ones
     variable.borrow_mut().one_grads();
 
-    let mut queue: VecDeque<Rc<RefCell<CgFunction>>> = VecDeque::new();
-    queue.push_back(variable.borrow().parent.as_ref().unwrap().clone());
-    loop {
-        if queue.is_empty() {
-            break;
+    // each function runs exactly once after all of its consumers, so that
+    // it can be freed right after
+    for function in functions {
+        function.borrow_mut().backward();
+        if !retain_graph {
+            function.borrow_mut().free();
+        }
+    }
+}
+
+// returns gradients of inputs for the given gradients of outputs, namely
+// vector-Jacobian products, while .grad of every variable is left untouched
+// and the graph is kept, and panics when the graph has already been freed
+pub fn grad(
+    outputs: &[Rc<RefCell<Variable>>],
+    output_grads: &[Rc<RefCell<Variable>>],
+    inputs: &[Rc<RefCell<Variable>>],
+) -> Vec<Variable> {
+    assert_eq!(outputs.len(), output_grads.len());
+    let output_grads: Vec<Vec<f32>> = outputs
+        .iter()
+        .zip(output_grads.iter())
+        .map(|(output, output_grad)| {
+            assert_eq!(output.borrow().shape, output_grad.borrow().shape);
+            output_grad.borrow().data.clone()
+        })
+        .collect();
+
+    let roots = outputs
+        .iter()
+        .filter_map(|output| output.borrow().parent.clone())
+        .collect();
+    let functions = topological_sort(roots);
+
+    // fail on freed graphs before any gradient is zeroed
+    for function in functions.iter() {
+        function.borrow().check_not_freed();
+    }
+
+    // every gradient buffer written during backward
+    let mut visited: HashSet<*const RefCell<Variable>> = HashSet::new();
+    let mut variables = vec![];
+    for function in functions.iter() {
+        let function = function.borrow();
+        let connected = function
+            .get_inputs()
+            .iter()
+            .chain(function.get_outputs().iter());
+        for variable in connected {
+            if visited.insert(Rc::as_ptr(variable)) {
+                variables.push(variable.clone());
+            }
+        }
+    }
+    for variable in outputs.iter().chain(inputs.iter()) {
+        if visited.insert(Rc::as_ptr(variable)) {
+            variables.push(variable.clone());
         }
-        let function = queue.pop_front().unwrap();
+    }
+
+    // compute gradients from zero and restore the original ones afterwards
+    let mut saved_grads = vec![];
+    for variable in variables.iter() {
+        let mut variable = variable.borrow_mut();
+        saved_grads.push(variable.grad.clone());
+        variable.zero_grads();
+    }
 
+    for (output, output_grad) in outputs.iter().zip(output_grads.iter()) {
+        let mut output = output.borrow_mut();
+        for (grad, value) in output.grad.iter_mut().zip(output_grad.iter()) {
+            *grad += value;
+        }
+    }
+    for function in functions {
         function.borrow_mut().backward();
+    }
 
-        for input in function.borrow_mut().get_inputs().iter() {
-            if !input.borrow().need_grad {
-                continue;
+    let grads = inputs
+        .iter()
+        .map(|input| {
+            let input = input.borrow();
+            let mut grad = Variable::new(input.shape.clone());
+            grad.set_data(&input.grad);
+            grad.set_need_grad(false);
+            grad
+        })
+        .collect();
+
+    for (variable, saved_grad) in variables.iter().zip(saved_grads) {
+        variable.borrow_mut().grad = saved_grad;
+    }
+    grads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function::FunctionImpl;
+    use crate::functions as F;
+
+    #[test]
+    fn backward_diamond_graph() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 3])));
+        x.borrow_mut().set_data(&[1.0, -2.0, 3.0]);
+
+        // x -> h -> (a, b) -> y
+        let h = F::neg(x.clone());
+        let a = F::square(h.clone());
+        let b = F::neg(h.clone());
+        let y = F::add(a, b);
+        backward(y);
+
+        // dy/dx = -(2h - 1) = 2x + 1
+        assert_eq!(x.borrow().grad, vec![3.0, -3.0, 7.0]);
+        assert_eq!(h.borrow().grad, vec![-3.0, 3.0, -7.0]);
+    }
+
+    #[test]
+    fn backward_shared_parameters() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 2])));
+        let w = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+        w.borrow_mut().set_data(&[1.0, 0.0, 0.0, 1.0]);
+
+        // y = mean((x @ w) @ w)
+        let h = F::matmul(x, w.clone());
+        let y = F::mean(F::matmul(h, w.clone()), &[], false);
+        backward(y);
+
+        // dy/dw = x^T @ (g @ w^T) + (x @ w)^T @ g with g = 0.5
+        assert_eq!(w.borrow().grad, vec![1.0, 1.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn backward_repeated_paths() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+
+        // a = -x, b = a + a, y = b + b = 4a
+        let a = F::neg(x.clone());
+        let b = F::add(a.clone(), a.clone());
+        let y = F::add(b.clone(), b);
+        backward(y);
+
+        assert_eq!(x.borrow().grad, vec![-4.0, -4.0]);
+        assert_eq!(a.borrow().grad, vec![4.0, 4.0]);
+    }
+
+    #[test]
+    fn backward_aliased_inputs() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![3])));
+        x.borrow_mut().set_data(&[1.0, -2.0, 3.0]);
+
+        // y = x * x, dy/dx = 2x
+        backward(F::mul(x.clone(), x.clone()));
+        assert_eq!(x.borrow().grad, vec![2.0, -4.0, 6.0]);
+
+        let w = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        w.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0]);
+
+        // y = w @ w, dy/dw = g @ w^T + w^T @ g with g = 1
+        backward(F::matmul(w.clone(), w.clone()));
+        assert_eq!(w.borrow().grad, vec![7.0, 11.0, 9.0, 13.0]);
+    }
+
+    #[test]
+    fn no_grad_guard() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+
+        {
+            let _guard = no_grad();
+            assert!(!is_grad_enabled());
+            let y = F::square(x.clone());
+            assert_eq!(y.borrow().data, vec![1.0, 4.0]);
+            assert!(y.borrow().parent.is_none());
+            assert!(!y.borrow().need_grad);
+
+            // inputs are not retained
+            assert_eq!(Rc::strong_count(&x), 1);
+
+            // guards nest
+            drop(no_grad());
+            assert!(!is_grad_enabled());
+        }
+        assert!(is_grad_enabled());
+
+        // outputs computed without gradients act as constants
+        let c = {
+            let _guard = no_grad();
+            F::neg(x.clone())
+        };
+        let y = F::mul(x.clone(), c);
+        backward(y);
+        assert_eq!(x.borrow().grad, vec![-1.0, -2.0]);
+    }
+
+    #[test]
+    fn retain_graph() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+
+        let y = F::sum(F::square(x.clone()), &[], false);
+        backward_with(y.clone(), true);
+        assert_eq!(x.borrow().grad, vec![2.0, 4.0]);
+
+        // gradients accumulate
+        backward(y.clone());
+        assert_eq!(x.borrow().grad, vec![4.0, 8.0]);
+        assert!(y.borrow().parent.as_ref().unwrap().borrow().is_freed());
+    }
+
+    #[test]
+    #[should_panic(expected = "already been freed")]
+    fn backward_freed_graph() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        let y = F::sum(F::square(x), &[], false);
+        backward(y.clone());
+        backward(y);
+    }
+
+    #[test]
+    fn free_intermediate_variables() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        let h = F::square(x.clone());
+        let weak_h = Rc::downgrade(&h);
+        let y = F::sum(h, &[], false);
+
+        // the graph holds intermediate variables until backward
+        assert!(weak_h.upgrade().is_some());
+        backward(y);
+        assert!(weak_h.upgrade().is_none());
+        assert_eq!(Rc::strong_count(&x), 1);
+    }
+
+    #[test]
+    fn free_function_state() {
+        // holds a buffer saved for backward, like the mask of dropout
+        struct Cache {
+            buffer: Rc<Vec<f32>>,
+        }
+
+        impl FunctionImpl for Cache {
+            fn forward_impl(&mut self, _: &[Rc<RefCell<Variable>>], _: &[Rc<RefCell<Variable>>]) {}
+
+            fn backward_impl(&mut self, _: &[Rc<RefCell<Variable>>], _: &[Rc<RefCell<Variable>>]) {
+                assert_eq!(self.buffer.len(), 2);
             }
-            let borrowed_input = input.borrow();
-            let parent = borrowed_input.parent.as_ref();
-            if let Some(p) = parent {
-                queue.push_back(p.clone());
+
+            fn get_name(&self) -> &str {
+                "Cache"
             }
         }
+
+        let buffer = Rc::new(vec![0.0; 2]);
+        let weak_buffer = Rc::downgrade(&buffer);
+        let y = Rc::new(RefCell::new(Variable::new(vec![1])));
+        let function = Rc::new(RefCell::new(CgFunction::new(
+            vec![],
+            vec![y.clone()],
+            Box::new(Cache { buffer }),
+        )));
+        y.borrow_mut().set_parent(function.clone());
+
+        backward_with(y.clone(), true);
+        assert!(weak_buffer.upgrade().is_some());
+        backward(y.clone());
+        assert!(weak_buffer.upgrade().is_none());
+
+        // the name is kept for the error on another backward
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| backward(y)));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.starts_with("backward through Cache"));
+    }
+
+    #[test]
+    fn detach() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+
+        // y = x * stop_gradient(x)
+        let c = Rc::new(RefCell::new(F::neg(x.clone()).borrow().detach()));
+        assert!(c.borrow().parent.is_none());
+        assert_eq!(c.borrow().data, vec![-1.0, -2.0]);
+        backward(F::mul(x.clone(), c));
+        assert_eq!(x.borrow().grad, vec![-1.0, -2.0]);
+    }
+
+    #[test]
+    fn vector_jacobian_product() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1, 2])));
+        let w = Rc::new(RefCell::new(Variable::new(vec![2, 2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+        w.borrow_mut().set_data(&[1.0, 2.0, 3.0, 4.0]);
+        x.borrow_mut().set_grad(&[10.0, 10.0]);
+
+        let h = F::matmul(x.clone(), w.clone());
+        let y = F::square(h.clone());
+        let loss = F::sum(y.clone(), &[], false);
+        let v = Rc::new(RefCell::new(Variable::new(vec![1, 2])));
+        v.borrow_mut().set_data(&[1.0, 0.5]);
+
+        // h = [7, 10], v * dy/dh = [14, 10], then v * dy/dh @ w^T
+        let grads = grad(&[y], &[v], &[x.clone(), h.clone()]);
+        assert_eq!(grads[0].data, vec![34.0, 82.0]);
+        assert_eq!(grads[0].shape, vec![1, 2]);
+        assert_eq!(grads[1].data, vec![14.0, 10.0]);
+
+        // no gradient buffer is modified
+        assert_eq!(x.borrow().grad, vec![10.0, 10.0]);
+        assert_eq!(w.borrow().grad, vec![0.0; 4]);
+        assert_eq!(h.borrow().grad, vec![0.0; 2]);
+
+        // the graph is kept
+        backward(loss);
+        assert_eq!(w.borrow().grad, vec![14.0, 20.0, 28.0, 40.0]);
+    }
+
+    #[test]
+    fn grad_multiple_outputs() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        let z = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+
+        // d(x^2 + (-x))/dx = 2x - 1, and z is unreachable
+        let a = F::square(x.clone());
+        let b = F::neg(x.clone());
+        let ones = Rc::new(RefCell::new(Variable::new(vec![2])));
+        ones.borrow_mut().ones();
+        let grads = grad(&[a, b], &[ones.clone(), ones], &[x, z]);
+        assert_eq!(grads[0].data, vec![1.0, 3.0]);
+        assert_eq!(grads[1].data, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn grad_freed_graph() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_data(&[1.0, 2.0]);
+        let y = F::sum(F::square(x.clone()), &[], false);
+        backward(y.clone());
+
+        // panics without touching gradients from the earlier backward
+        let ones = Rc::new(RefCell::new(Variable::new(vec![1])));
+        ones.borrow_mut().ones();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            grad(std::slice::from_ref(&y), &[ones], std::slice::from_ref(&x))
+        }));
+        assert!(result.is_err());
+        assert_eq!(x.borrow().grad, vec![2.0, 4.0]);
+        assert_eq!(y.borrow().grad, vec![1.0]);
     }
 }
@@ -0,0 +1,402 @@
+use rand::distributions::StandardNormal;
+use rand::Rng;
+use std::f64::consts::SQRT_2;
+
+use crate::random;
+use crate::variable::Variable;
+
+// fans are given by layers since the weight layout differs between layers
+pub trait Initializer {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, fan_out: usize);
+}
+
+pub struct Constant {
+    pub value: f32,
+}
+
+impl Initializer for Constant {
+    fn initialize(&self, variable: &mut Variable, _fan_in: usize, _fan_out: usize) {
+        variable.data.fill(self.value);
+    }
+}
+
+pub struct Uniform {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Initializer for Uniform {
+    fn initialize(&self, variable: &mut Variable, _fan_in: usize, _fan_out: usize) {
+        fill_uniform(&mut variable.data, self.low, self.high);
+    }
+}
+
+pub struct Normal {
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl Initializer for Normal {
+    fn initialize(&self, variable: &mut Variable, _fan_in: usize, _fan_out: usize) {
+        fill_normal(&mut variable.data, self.mean, self.std);
+    }
+}
+
+pub struct TruncatedNormal {
+    pub mean: f32,
+    pub std: f32,
+    // absolute bounds of the samples
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Initializer for TruncatedNormal {
+    fn initialize(&self, variable: &mut Variable, _fan_in: usize, _fan_out: usize) {
+        assert!(self.low < self.high);
+
+        // inverse transform sampling, which unlike rejection sampling takes
+        // constant time for bounds far from the mean
+        let cdf = |x: f32| 0.5 * (1.0 + erf((x - self.mean) as f64 / (self.std as f64 * SQRT_2)));
+        let (cdf_low, cdf_high) = (cdf(self.low), cdf(self.high));
+        random::with_rng(|rng| {
+            for value in variable.data.iter_mut() {
+                let u = cdf_low + (cdf_high - cdf_low) * rng.gen::<f64>();
+                let sample = self.mean as f64 + self.std as f64 * SQRT_2 * erfinv(2.0 * u - 1.0);
+
+                // rounding in the tails can step outside of the bounds
+                *value = (sample as f32).clamp(self.low, self.high);
+            }
+        });
+    }
+}
+
+// Abramowitz and Stegun 7.1.26 with absolute error below 1.5e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+// single precision approximation by Giles, "Approximating the erfinv function"
+fn erfinv(x: f64) -> f64 {
+    let mut w = -((1.0 - x) * (1.0 + x)).ln();
+    let p = if w < 5.0 {
+        w -= 2.5;
+        let mut p = 2.81022636e-08;
+        for c in [
+            3.43273939e-07,
+            -3.5233877e-06,
+            -4.39150654e-06,
+            0.00021858087,
+            -0.00125372503,
+            -0.00417768164,
+            0.246640727,
+            1.50140941,
+        ] {
+            p = c + p * w;
+        }
+        p
+    } else {
+        w = w.sqrt() - 3.0;
+        let mut p = -0.000200214257;
+        for c in [
+            0.000100950558,
+            0.00134934322,
+            -0.00367342844,
+            0.00573950773,
+            -0.0076224613,
+            0.00943887047,
+            1.00167406,
+            2.83297682,
+        ] {
+            p = c + p * w;
+        }
+        p
+    };
+    p * x
+}
+
+pub struct XavierUniform {
+    pub gain: f32,
+}
+
+impl Initializer for XavierUniform {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, fan_out: usize) {
+        let bound = self.gain * (6.0 / (fan_in + fan_out) as f32).sqrt();
+        fill_uniform(&mut variable.data, -bound, bound);
+    }
+}
+
+pub struct XavierNormal {
+    pub gain: f32,
+}
+
+impl Initializer for XavierNormal {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, fan_out: usize) {
+        let std = self.gain * (2.0 / (fan_in + fan_out) as f32).sqrt();
+        fill_normal(&mut variable.data, 0.0, std);
+    }
+}
+
+pub struct KaimingUniform {
+    pub gain: f32,
+}
+
+impl Initializer for KaimingUniform {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, _fan_out: usize) {
+        let bound = self.gain * (3.0 / fan_in as f32).sqrt();
+        fill_uniform(&mut variable.data, -bound, bound);
+    }
+}
+
+pub struct KaimingNormal {
+    pub gain: f32,
+}
+
+impl Initializer for KaimingNormal {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, _fan_out: usize) {
+        let std = self.gain / (fan_in as f32).sqrt();
+        fill_normal(&mut variable.data, 0.0, std);
+    }
+}
+
+// the first dimension is treated as rows and the rest as columns
+pub struct Orthogonal {
+    pub gain: f32,
+}
+
+impl Initializer for Orthogonal {
+    fn initialize(&self, variable: &mut Variable, _fan_in: usize, _fan_out: usize) {
+        let rows = variable.shape[0];
+        let cols = variable.size() / rows;
+        let (long, short) = if rows >= cols {
+            (rows, cols)
+        } else {
+            (cols, rows)
+        };
+
+        // orthonormalize columns of a (long, short) gaussian matrix
+        // with modified Gram-Schmidt
+        let mut q = vec![0.0; long * short];
+        fill_normal(&mut q, 0.0, 1.0);
+        for j in 0..short {
+            for k in 0..j {
+                let mut dot = 0.0;
+                for i in 0..long {
+                    dot += q[i * short + j] * q[i * short + k];
+                }
+                for i in 0..long {
+                    q[i * short + j] -= dot * q[i * short + k];
+                }
+            }
+            let mut norm = 0.0;
+            for i in 0..long {
+                norm += q[i * short + j] * q[i * short + j];
+            }
+            let norm = f32::sqrt(norm);
+            for i in 0..long {
+                q[i * short + j] /= norm;
+            }
+        }
+
+        for r in 0..rows {
+            for c in 0..cols {
+                let value = if rows >= cols {
+                    q[r * short + c]
+                } else {
+                    q[c * short + r]
+                };
+                variable.data[r * cols + c] = self.gain * value;
+            }
+        }
+    }
+}
+
+pub struct Custom<T: Fn(&mut Variable, usize, usize)> {
+    pub function: T,
+}
+
+impl<T: Fn(&mut Variable, usize, usize)> Initializer for Custom<T> {
+    fn initialize(&self, variable: &mut Variable, fan_in: usize, fan_out: usize) {
+        (self.function)(variable, fan_in, fan_out);
+    }
+}
+
+fn fill_uniform(data: &mut [f32], low: f32, high: f32) {
+    random::with_rng(|rng| {
+        for value in data.iter_mut() {
+            *value = low + (high - low) * rng.gen::<f32>();
+        }
+    });
+}
+
+fn fill_normal(data: &mut [f32], mean: f32, std: f32) {
+    random::with_rng(|rng| {
+        for value in data.iter_mut() {
+            *value = mean + std * rng.sample(StandardNormal) as f32;
+        }
+    });
+}
+
+pub fn constant(value: f32) -> Box<dyn Initializer> {
+    Box::new(Constant { value })
+}
+
+pub fn zeros() -> Box<dyn Initializer> {
+    constant(0.0)
+}
+
+pub fn ones() -> Box<dyn Initializer> {
+    constant(1.0)
+}
+
+pub fn uniform(low: f32, high: f32) -> Box<dyn Initializer> {
+    Box::new(Uniform { low, high })
+}
+
+pub fn normal(mean: f32, std: f32) -> Box<dyn Initializer> {
+    Box::new(Normal { mean, std })
+}
+
+pub fn truncated_normal(mean: f32, std: f32, low: f32, high: f32) -> Box<dyn Initializer> {
+    Box::new(TruncatedNormal {
+        mean,
+        std,
+        low,
+        high,
+    })
+}
+
+pub fn xavier_uniform(gain: f32) -> Box<dyn Initializer> {
+    Box::new(XavierUniform { gain })
+}
+
+pub fn xavier_normal(gain: f32) -> Box<dyn Initializer> {
+    Box::new(XavierNormal { gain })
+}
+
+pub fn kaiming_uniform(gain: f32) -> Box<dyn Initializer> {
+    Box::new(KaimingUniform { gain })
+}
+
+pub fn kaiming_normal(gain: f32) -> Box<dyn Initializer> {
+    Box::new(KaimingNormal { gain })
+}
+
+pub fn orthogonal(gain: f32) -> Box<dyn Initializer> {
+    Box::new(Orthogonal { gain })
+}
+
+pub fn custom<T: Fn(&mut Variable, usize, usize) + 'static>(function: T) -> Box<dyn Initializer> {
+    Box::new(Custom { function })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean_std(data: &[f32]) -> (f32, f32) {
+        let mean = data.iter().sum::<f32>() / data.len() as f32;
+        let var = data.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / data.len() as f32;
+        (mean, var.sqrt())
+    }
+
+    #[test]
+    fn constant_initializer() {
+        let mut variable = Variable::new(vec![2, 3]);
+        constant(0.5).initialize(&mut variable, 2, 3);
+        assert_eq!(variable.data, vec![0.5; 6]);
+    }
+
+    #[test]
+    fn xavier_initializers() {
+        let mut variable = Variable::new(vec![100, 300]);
+        xavier_uniform(1.0).initialize(&mut variable, 100, 300);
+        let bound = (6.0f32 / 400.0).sqrt();
+        assert!(variable.data.iter().all(|v| v.abs() <= bound));
+
+        xavier_normal(2.0).initialize(&mut variable, 100, 300);
+        let (mean, std) = mean_std(&variable.data);
+        assert!(mean.abs() < 0.01);
+        assert!((std - 2.0 * (2.0f32 / 400.0).sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn kaiming_initializers() {
+        let mut variable = Variable::new(vec![200, 100]);
+        kaiming_uniform(2.0f32.sqrt()).initialize(&mut variable, 200, 100);
+        let bound = (6.0f32 / 200.0).sqrt();
+        assert!(variable.data.iter().all(|v| v.abs() <= bound));
+
+        kaiming_normal(2.0f32.sqrt()).initialize(&mut variable, 200, 100);
+        let (_, std) = mean_std(&variable.data);
+        assert!((std - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn truncated_normal_initializer() {
+        random::seed(0);
+        let mut variable = Variable::new(vec![1000]);
+        truncated_normal(0.0, 1.0, -0.5, 0.5).initialize(&mut variable, 1, 1);
+        assert!(variable.data.iter().all(|v| v.abs() <= 0.5));
+
+        // truncated to two standard deviations around the mean
+        let mut variable = Variable::new(vec![10000]);
+        truncated_normal(1.0, 2.0, -3.0, 5.0).initialize(&mut variable, 1, 1);
+        let (mean, std) = mean_std(&variable.data);
+        assert!((mean - 1.0).abs() < 0.05);
+        assert!((std - 2.0 * 0.8796).abs() < 0.05);
+
+        // bounds far from the mean terminate
+        truncated_normal(0.0, 0.01, 1.0, 2.0).initialize(&mut variable, 1, 1);
+        assert!(variable.data.iter().all(|v| (1.0..=2.0).contains(v)));
+    }
+
+    #[test]
+    fn orthogonal_initializer() {
+        for shape in [vec![4, 6], vec![6, 4]] {
+            let mut variable = Variable::new(shape.clone());
+            orthogonal(1.0).initialize(&mut variable, 1, 1);
+
+            // rows or columns are orthonormal
+            let (rows, cols) = (shape[0], shape[1]);
+            let (n, m) = if rows >= cols {
+                (cols, rows)
+            } else {
+                (rows, cols)
+            };
+            for a in 0..n {
+                for b in 0..n {
+                    let mut dot = 0.0;
+                    for k in 0..m {
+                        dot += if rows >= cols {
+                            variable.data[k * cols + a] * variable.data[k * cols + b]
+                        } else {
+                            variable.data[a * cols + k] * variable.data[b * cols + k]
+                        };
+                    }
+                    let expected = if a == b { 1.0 } else { 0.0 };
+                    assert!((dot - expected).abs() < 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_initializer() {
+        let mut variable = Variable::new(vec![3]);
+        custom(|variable, fan_in, _| variable.data.fill(fan_in as f32)).initialize(
+            &mut variable,
+            7,
+            1,
+        );
+        assert_eq!(variable.data, vec![7.0; 3]);
+    }
+}
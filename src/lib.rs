@@ -2,7 +2,14 @@ pub mod datasets;
 mod function;
 pub mod functions;
 pub mod graph;
+pub mod init;
+pub mod module;
 mod optimizer;
 pub mod optimizers;
 pub mod parametric_functions;
+pub mod random;
+mod scheduler;
+pub mod schedulers;
+pub mod serialization;
+pub mod tensor;
 pub mod variable;
@@ -1,19 +1,32 @@
 use miniature::datasets::MNISTLoader;
 use miniature::functions as F;
-use miniature::graph::backward;
+use miniature::graph::{backward, no_grad};
+use miniature::module::Module;
 use miniature::optimizers as S;
 use miniature::parametric_functions as PF;
+use miniature::random;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    random::seed(0);
+
     let dataset = MNISTLoader::new("datasets")?;
     let (test_x, test_t) = dataset.get_test_data();
 
-    let fc1 = PF::linear(28 * 28, 256);
-    let fc2 = PF::linear(256, 10);
+    let model = PF::sequential()
+        .then(PF::linear(28 * 28, 256))
+        .then_fn(F::relu)
+        .then(PF::linear(256, 10));
 
     let mut optim = S::adam(0.001, (0.9, 0.999), 1e-8);
-    optim.set_params(fc1.get_params());
-    optim.set_params(fc2.get_params());
+    optim.set_params(model.parameters());
+
+    // resume from the last checkpoint with --resume
+    let checkpoint = "mnist_mlp.bin";
+    let optim_checkpoint = "mnist_mlp_optim.bin";
+    if std::env::args().any(|arg| arg == "--resume") {
+        model.load_state_dict(checkpoint, true)?;
+        optim.load_state_dict(optim_checkpoint)?;
+    }
 
     let mut iter = 0;
     loop {
@@ -21,8 +34,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let onehot_t = F::onehot(t, 10);
 
         // forward
-        let h = F::relu(fc1.call(x));
-        let output = fc2.call(h);
+        let output = model.forward(x);
 
         // loss
         let loss = F::cross_entropy_loss(output, onehot_t);
@@ -33,9 +45,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         iter += 1;
         if iter % 100 == 0 {
-            // test
-            let h = F::relu(fc1.call(test_x.clone()));
-            let output = F::argmax(fc2.call(h));
+            // test without building the graph
+            let _guard = no_grad();
+            let output = F::argmax(model.forward(test_x.clone()));
 
             let mut count = 0;
             let test_size = output.borrow().shape[0];
@@ -48,6 +60,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             let accuracy = (count as f32) / (test_size as f32);
             println!("Iteration {}: Accuracy={}", iter, accuracy);
+
+            model.save_state_dict(checkpoint)?;
+            optim.save_state_dict(optim_checkpoint)?;
         }
 
         if iter == 100000 {
@@ -0,0 +1,345 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::serialization::{copy_state_dict, native};
+use crate::variable::Variable;
+
+// modules are composed by returning submodules from children, and
+// parameters are collected hierarchically with dotted names in registration
+// order, namely local ones followed by those of children in order
+pub trait Module {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>>;
+
+    // parameters owned directly by this module
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![]
+    }
+
+    // non-trainable states owned directly by this module
+    fn local_buffers(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![]
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<(String, &mut dyn Module)> {
+        vec![]
+    }
+
+    // switches the mode of this module only
+    fn set_training(&mut self, _training: bool) {}
+
+    fn named_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        let mut params = self.local_parameters();
+        for (child_name, child) in self.children() {
+            for (name, param) in child.named_parameters() {
+                params.push((format!("{}.{}", child_name, name), param));
+            }
+        }
+        params
+    }
+
+    fn parameters(&self) -> Vec<Rc<RefCell<Variable>>> {
+        self.named_parameters()
+            .into_iter()
+            .map(|(_, param)| param)
+            .collect()
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        let mut buffers = self.local_buffers();
+        for (child_name, child) in self.children() {
+            for (name, buffer) in child.named_buffers() {
+                buffers.push((format!("{}.{}", child_name, name), buffer));
+            }
+        }
+        buffers
+    }
+
+    fn buffers(&self) -> Vec<Rc<RefCell<Variable>>> {
+        self.named_buffers()
+            .into_iter()
+            .map(|(_, buffer)| buffer)
+            .collect()
+    }
+
+    // parameters and buffers together as saved in checkpoints
+    fn state_dict(&self) -> BTreeMap<String, Rc<RefCell<Variable>>> {
+        self.named_parameters()
+            .into_iter()
+            .chain(self.named_buffers())
+            .collect()
+    }
+
+    fn save_state_dict(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        native::save(path, &self.state_dict())
+    }
+
+    // strict loading requires the checkpoint keys to match exactly, while
+    // non-strict loading ignores missing and unexpected keys; shapes of
+    // matched keys must agree in both modes
+    fn load_state_dict(&self, path: &str, strict: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let state_dict = native::load(path)?;
+        copy_state_dict(&self.state_dict(), &state_dict, strict)?;
+        Ok(())
+    }
+
+    fn train(&mut self) {
+        self.set_training(true);
+        for (_, child) in self.children_mut() {
+            child.train();
+        }
+    }
+
+    fn eval(&mut self) {
+        self.set_training(false);
+        for (_, child) in self.children_mut() {
+            child.eval();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions as F;
+    use crate::graph::backward;
+    use crate::parametric_functions as PF;
+    use crate::parametric_functions::{BatchNorm, Dropout, Linear};
+    use crate::serialization::{temp_path, StateDictError};
+
+    struct Block {
+        fc: Box<Linear>,
+        bn: Box<BatchNorm>,
+    }
+
+    impl Module for Block {
+        fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+            F::relu(self.bn.forward(self.fc.forward(x)))
+        }
+
+        fn children(&self) -> Vec<(String, &dyn Module)> {
+            vec![
+                ("fc".to_string(), &*self.fc as &dyn Module),
+                ("bn".to_string(), &*self.bn as &dyn Module),
+            ]
+        }
+
+        fn children_mut(&mut self) -> Vec<(String, &mut dyn Module)> {
+            vec![
+                ("fc".to_string(), &mut *self.fc as &mut dyn Module),
+                ("bn".to_string(), &mut *self.bn as &mut dyn Module),
+            ]
+        }
+    }
+
+    struct Net {
+        block: Block,
+        drop: Box<Dropout>,
+        fc2: Box<Linear>,
+        scale: Rc<RefCell<Variable>>,
+    }
+
+    impl Module for Net {
+        fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+            let h = self.drop.forward(self.block.forward(x));
+            F::mul(self.fc2.forward(h), self.scale.clone())
+        }
+
+        fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+            vec![("scale".to_string(), self.scale.clone())]
+        }
+
+        fn children(&self) -> Vec<(String, &dyn Module)> {
+            vec![
+                ("block".to_string(), &self.block as &dyn Module),
+                ("drop".to_string(), &*self.drop as &dyn Module),
+                ("fc2".to_string(), &*self.fc2 as &dyn Module),
+            ]
+        }
+
+        fn children_mut(&mut self) -> Vec<(String, &mut dyn Module)> {
+            vec![
+                ("block".to_string(), &mut self.block as &mut dyn Module),
+                ("drop".to_string(), &mut *self.drop as &mut dyn Module),
+                ("fc2".to_string(), &mut *self.fc2 as &mut dyn Module),
+            ]
+        }
+    }
+
+    fn net() -> Net {
+        Net {
+            block: Block {
+                fc: PF::linear(4, 8),
+                bn: PF::batch_norm1d(8),
+            },
+            drop: PF::dropout(0.5),
+            fc2: PF::linear(8, 2),
+            scale: Rc::new(RefCell::new(Variable::new(vec![1]))),
+        }
+    }
+
+    #[test]
+    fn named_parameters() {
+        let model = net();
+        let (names, params): (Vec<String>, Vec<_>) = model.named_parameters().into_iter().unzip();
+        assert_eq!(
+            names,
+            vec![
+                "scale",
+                "block.fc.weight",
+                "block.fc.bias",
+                "block.bn.weight",
+                "block.bn.bias",
+                "fc2.weight",
+                "fc2.bias",
+            ]
+        );
+        assert!(Rc::ptr_eq(&params[5], &model.fc2.get_params()[0]));
+
+        let names: Vec<String> = model
+            .named_buffers()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["block.bn.running_mean", "block.bn.running_var"]);
+        assert_eq!(model.parameters().len(), 7);
+        assert_eq!(model.buffers().len(), 2);
+    }
+
+    #[test]
+    fn train_eval() {
+        let mut model = net();
+        model.scale.borrow_mut().ones();
+        let x = Rc::new(RefCell::new(Variable::rand(vec![3, 4])));
+        backward(model.forward(x.clone()));
+
+        // evaluation mode propagates to every submodule
+        model.eval();
+        assert!(!model.block.bn.is_training());
+        assert!(!model.drop.is_training());
+        let y1 = model.forward(x.clone());
+        let y2 = model.forward(x);
+        assert_eq!(y1.borrow().data, y2.borrow().data);
+
+        model.train();
+        assert!(model.block.bn.is_training());
+        assert!(model.drop.is_training());
+    }
+
+    #[test]
+    fn save_load_state_dict() {
+        let path = temp_path("state_dict.bin");
+        let model = net();
+        model.scale.borrow_mut().set_data(&[2.0]);
+        let x = Rc::new(RefCell::new(Variable::rand(vec![3, 4])));
+        model.forward(x);
+        model.save_state_dict(&path).unwrap();
+
+        // parameters and buffers are restored
+        let other = net();
+        other.load_state_dict(&path, true).unwrap();
+        for (name, variable) in model.state_dict() {
+            let loaded = &other.state_dict()[&name];
+            assert_eq!(loaded.borrow().shape, variable.borrow().shape);
+            assert_eq!(loaded.borrow().data, variable.borrow().data);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_state_dict_key_matching() {
+        let path = temp_path("key_matching.bin");
+        let block = Block {
+            fc: PF::linear(4, 8),
+            bn: PF::batch_norm1d(8),
+        };
+        block.save_state_dict(&path).unwrap();
+
+        // keys of the block are missing in the network
+        let model = net();
+        let error = model.load_state_dict(&path, true).unwrap_err();
+        match error.downcast_ref::<StateDictError>() {
+            Some(StateDictError::MissingKeys(keys)) => assert!(keys.contains(&"scale".to_string())),
+            _ => panic!("unexpected error: {}", error),
+        }
+
+        // non-strict loading skips unknown keys
+        let other = Block {
+            fc: PF::linear(4, 8),
+            bn: PF::batch_norm1d(8),
+        };
+        let weight = other.fc.get_params()[0].borrow().data.clone();
+        model.save_state_dict(&path).unwrap();
+        assert!(other.load_state_dict(&path, true).is_err());
+        other.load_state_dict(&path, false).unwrap();
+        assert_eq!(other.fc.get_params()[0].borrow().data, weight);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_state_dict_shape_mismatch() {
+        let path = temp_path("shape_mismatch.bin");
+        let model = net();
+        model.save_state_dict(&path).unwrap();
+
+        let other = net();
+        let before = other.block.fc.get_params()[0].borrow().data.clone();
+        let other = Net {
+            fc2: PF::linear(8, 3),
+            ..other
+        };
+        let error = other.load_state_dict(&path, false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "shape mismatch for fc2.bias: expected [1, 3] but got [1, 2]"
+        );
+
+        // nothing is loaded on errors
+        assert_eq!(other.block.fc.get_params()[0].borrow().data, before);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_state_dict_corrupt_file() {
+        let path = temp_path("corrupt.bin");
+
+        // header of a single entry named "a" with the given name length and shape
+        let entry = |length: u64, shape: &[u64]| {
+            let mut bytes = b"MINI".to_vec();
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.push(b'a');
+            bytes.push(0);
+            bytes.extend_from_slice(&(shape.len() as u64).to_le_bytes());
+            for dim_size in shape {
+                bytes.extend_from_slice(&dim_size.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0; 16]);
+            bytes
+        };
+
+        // sizes beyond the file are rejected without allocating them
+        for bytes in [
+            entry(u64::MAX, &[2]),
+            entry(1, &[1 << 40, 1 << 40]),
+            entry(1, &[u64::MAX, 2]),
+            entry(1, &[5]),
+        ] {
+            std::fs::write(&path, bytes).unwrap();
+            let error = net().load_state_dict(&path, false).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<StateDictError>(),
+                Some(StateDictError::InvalidFormat(_))
+            ));
+        }
+
+        std::fs::write(&path, entry(1, &[4])).unwrap();
+        assert!(net().load_state_dict(&path, false).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,38 +1,541 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::rc::Rc;
 
+use crate::serialization::{native, StateDictError};
 use crate::variable::Variable;
 
 pub trait OptimizerImpl {
+    // type name stored in state dicts, e.g. "Adam"
+    fn name(&self) -> &str;
+
     fn update(&mut self, params: &[Rc<RefCell<Variable>>]);
+
+    fn lr(&self) -> f32;
+
+    fn set_lr(&mut self, lr: f32);
+
+    // sets a hyperparameter by name, e.g. "weight_decay"
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError>;
+
+    // checks hyperparameters that depend on each other once all options
+    // are set
+    fn validate_options(&self) -> Result<(), OptionError> {
+        Ok(())
+    }
+
+    // instance with the same hyperparameters and no states
+    fn new_group(&self) -> Box<dyn OptimizerImpl>;
+
+    // hyperparameters and per-parameter states keyed by name
+    fn state_dict(&self) -> BTreeMap<String, Variable>;
+
+    // integers such as step counts, stored exactly as strings
+    fn metadata(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    // per-parameter states must match the sizes of the parameters
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError>;
 }
 
-pub struct Optimizer {
+// hyperparameter given to add_param_group, converted from f32 or bool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionValue {
+    Float(f32),
+    Bool(bool),
+}
+
+impl From<f32> for OptionValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for OptionValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl OptionValue {
+    pub(crate) fn float(self, name: &str) -> Result<f32, OptionError> {
+        match self {
+            Self::Float(value) => Ok(value),
+            Self::Bool(_) => Err(OptionError::TypeMismatch {
+                name: name.to_string(),
+                expected: "f32",
+            }),
+        }
+    }
+
+    pub(crate) fn bool(self, name: &str) -> Result<bool, OptionError> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            Self::Float(_) => Err(OptionError::TypeMismatch {
+                name: name.to_string(),
+                expected: "bool",
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OptionError {
+    UnknownOption {
+        optimizer: String,
+        name: String,
+    },
+    TypeMismatch {
+        name: String,
+        expected: &'static str,
+    },
+    InvalidOptions(String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOption { optimizer, name } => {
+                write!(f, "unknown option {} for {}", name, optimizer)
+            }
+            Self::TypeMismatch { name, expected } => {
+                write!(f, "option {} expects a value of type {}", name, expected)
+            }
+            Self::InvalidOptions(message) => write!(f, "invalid options: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+pub(crate) fn unknown_option(optimizer: &str, name: &str) -> OptionError {
+    OptionError::UnknownOption {
+        optimizer: optimizer.to_string(),
+        name: name.to_string(),
+    }
+}
+
+pub(crate) fn scalar_state(value: f32) -> Variable {
+    let mut variable = Variable::new(vec![1]);
+    variable.data[0] = value;
+    variable
+}
+
+pub(crate) fn get_state<'a>(
+    state_dict: &'a BTreeMap<String, Variable>,
+    key: &str,
+    shape: &[usize],
+) -> Result<&'a Variable, StateDictError> {
+    let variable = state_dict
+        .get(key)
+        .ok_or_else(|| StateDictError::MissingKeys(vec![key.to_string()]))?;
+    if variable.shape != shape {
+        return Err(StateDictError::ShapeMismatch {
+            key: key.to_string(),
+            expected: shape.to_vec(),
+            actual: variable.shape.clone(),
+        });
+    }
+    Ok(variable)
+}
+
+pub(crate) fn get_step_count(
+    metadata: &BTreeMap<String, String>,
+    key: &str,
+) -> Result<u32, StateDictError> {
+    let value = metadata
+        .get(key)
+        .ok_or_else(|| StateDictError::MissingKeys(vec![key.to_string()]))?;
+    value.parse().map_err(|_| {
+        StateDictError::InvalidFormat(format!("{} is not a step count: {}", key, value))
+    })
+}
+
+// per-parameter states of zeros
+pub(crate) fn zero_states(params: &[Rc<RefCell<Variable>>]) -> Vec<Vec<f32>> {
+    params
+        .iter()
+        .map(|param| vec![0.0; param.borrow().size()])
+        .collect()
+}
+
+// per-parameter states are stored as `{prefix}.{index}` in parameter order,
+// and are either absent before the first update or present for every
+// parameter with its size
+pub(crate) fn get_param_states(
+    state_dict: &BTreeMap<String, Variable>,
+    prefix: &str,
+    param_sizes: &[usize],
+) -> Result<Vec<Vec<f32>>, StateDictError> {
+    let mut states = vec![];
+    while let Some(state) = state_dict.get(&format!("{}.{}", prefix, states.len())) {
+        states.push(state.data.clone());
+    }
+    if states.is_empty() {
+        return Ok(states);
+    }
+    if states.len() < param_sizes.len() {
+        return Err(StateDictError::MissingKeys(vec![format!(
+            "{}.{}",
+            prefix,
+            states.len()
+        )]));
+    }
+    if states.len() > param_sizes.len() {
+        return Err(StateDictError::UnexpectedKeys(vec![format!(
+            "{}.{}",
+            prefix,
+            param_sizes.len()
+        )]));
+    }
+    for (i, (state, size)) in states.iter().zip(param_sizes.iter()).enumerate() {
+        if state.len() != *size {
+            return Err(StateDictError::ShapeMismatch {
+                key: format!("{}.{}", prefix, i),
+                expected: vec![*size],
+                actual: vec![state.len()],
+            });
+        }
+    }
+    Ok(states)
+}
+
+// states used together with others must cover the same parameters, since
+// get_param_states accepts each of them being absent
+pub(crate) fn check_param_states(
+    prefix: &str,
+    states: &[Vec<f32>],
+    len: usize,
+) -> Result<(), StateDictError> {
+    if states.len() < len {
+        return Err(StateDictError::MissingKeys(vec![format!(
+            "{}.{}",
+            prefix,
+            states.len()
+        )]));
+    }
+    if states.len() > len {
+        return Err(StateDictError::UnexpectedKeys(vec![format!(
+            "{}.{}",
+            prefix, len
+        )]));
+    }
+    Ok(())
+}
+
+pub(crate) fn insert_param_states(
+    state_dict: &mut BTreeMap<String, Variable>,
+    prefix: &str,
+    states: &[Vec<f32>],
+) {
+    for (i, state) in states.iter().enumerate() {
+        let mut variable = Variable::new(vec![state.len()]);
+        variable.set_data(state);
+        state_dict.insert(format!("{}.{}", prefix, i), variable);
+    }
+}
+
+// parameters sharing one optimizer instance, and thus its hyperparameters
+// and per-parameter states
+struct ParamGroup {
     params: Vec<Rc<RefCell<Variable>>>,
     optimizer_impl: Box<dyn OptimizerImpl>,
 }
 
+// group 0 is the default group which set_params adds to
+pub struct Optimizer {
+    groups: Vec<ParamGroup>,
+}
+
+const GROUP_PREFIX: &str = "param_groups";
+const TYPE_KEY: &str = "type";
+
+fn group_key(group: usize, key: String) -> String {
+    if group == 0 {
+        key
+    } else {
+        format!("{}.{}.{}", GROUP_PREFIX, group, key)
+    }
+}
+
+// splits entries by group, and collects keys of groups out of range
+fn split_groups<T>(
+    entries: BTreeMap<String, T>,
+    num_groups: usize,
+    unexpected: &mut Vec<String>,
+) -> Vec<BTreeMap<String, T>> {
+    let mut groups: Vec<BTreeMap<String, T>> = (0..num_groups).map(|_| BTreeMap::new()).collect();
+    for (key, value) in entries {
+        match split_group_key(&key, num_groups) {
+            Some((group, group_key)) => {
+                groups[group].insert(group_key, value);
+            }
+            None => unexpected.push(key),
+        }
+    }
+    groups
+}
+
+// returns the group index and the key within the group, or None for
+// groups out of range
+fn split_group_key(key: &str, num_groups: usize) -> Option<(usize, String)> {
+    let rest = match key
+        .strip_prefix(GROUP_PREFIX)
+        .and_then(|rest| rest.strip_prefix('.'))
+    {
+        Some(rest) => rest,
+        None => return Some((0, key.to_string())),
+    };
+    let (group, group_key) = rest.split_once('.')?;
+    let group = group.parse::<usize>().ok()?;
+    if group == 0 || group >= num_groups {
+        return None;
+    }
+    Some((group, group_key.to_string()))
+}
+
 impl Optimizer {
     pub fn new(optimizer_impl: Box<dyn OptimizerImpl>) -> Self {
         Self {
-            params: vec![],
-            optimizer_impl,
+            groups: vec![ParamGroup {
+                params: vec![],
+                optimizer_impl,
+            }],
         }
     }
 
     pub fn set_params(&mut self, params: Vec<Rc<RefCell<Variable>>>) {
         for param in params.iter() {
-            self.params.push(param.clone());
+            self.groups[0].params.push(param.clone());
+        }
+    }
+
+    // adds a group with hyperparameters of the default group overridden by
+    // options (e.g. [("lr", 0.01.into()), ("amsgrad", true.into())]), and
+    // returns the index of the group
+    //
+    // no group is added if any option is unknown or invalid
+    pub fn add_param_group(
+        &mut self,
+        params: Vec<Rc<RefCell<Variable>>>,
+        options: &[(&str, OptionValue)],
+    ) -> Result<usize, OptionError> {
+        let mut optimizer_impl = self.groups[0].optimizer_impl.new_group();
+        for (name, value) in options.iter() {
+            optimizer_impl.set_option(name, *value)?;
         }
+        optimizer_impl.validate_options()?;
+        self.groups.push(ParamGroup {
+            params,
+            optimizer_impl,
+        });
+        Ok(self.groups.len() - 1)
+    }
+
+    // sets a hyperparameter of an existing group while keeping its states,
+    // checked on a copy first so that the group is unchanged on errors
+    pub fn set_group_option(
+        &mut self,
+        group: usize,
+        name: &str,
+        value: OptionValue,
+    ) -> Result<(), OptionError> {
+        let mut copy = self.groups[group].optimizer_impl.new_group();
+        copy.set_option(name, value)?;
+        copy.validate_options()?;
+        self.groups[group].optimizer_impl.set_option(name, value)
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    fn params(&self) -> impl Iterator<Item = &Rc<RefCell<Variable>>> {
+        self.groups.iter().flat_map(|group| group.params.iter())
     }
 
     pub fn update(&mut self) {
-        self.optimizer_impl.update(&self.params);
+        for group in self.groups.iter_mut() {
+            group.optimizer_impl.update(&group.params);
+        }
+    }
+
+    // learning rate of the default group
+    pub fn lr(&self) -> f32 {
+        self.groups[0].optimizer_impl.lr()
+    }
+
+    // sets the learning rate of every group
+    pub fn set_lr(&mut self, lr: f32) {
+        for group in self.groups.iter_mut() {
+            group.optimizer_impl.set_lr(lr);
+        }
+    }
+
+    pub fn group_lr(&self, group: usize) -> f32 {
+        self.groups[group].optimizer_impl.lr()
+    }
+
+    pub fn set_group_lr(&mut self, group: usize, lr: f32) {
+        self.groups[group].optimizer_impl.set_lr(lr);
     }
 
     pub fn zero_grad(&mut self) {
-        for param in self.params.iter() {
+        for param in self.params() {
             param.borrow_mut().zero_grads();
         }
     }
+
+    // L2 norm of all gradients as if concatenated
+    pub fn grad_norm(&self) -> f32 {
+        self.grad_norm_with(2.0)
+    }
+
+    // p-norm of all gradients as if concatenated, where f32::INFINITY takes
+    // the largest absolute value
+    pub fn grad_norm_with(&self, norm_type: f32) -> f32 {
+        assert!(norm_type > 0.0);
+        let mut norm = 0.0;
+        for param in self.params() {
+            let grad = &param.borrow().grad;
+            if norm_type == f32::INFINITY {
+                norm = grad.iter().map(|g| g.abs()).fold(norm, f32::max);
+            } else {
+                norm += grad.iter().map(|g| g.abs().powf(norm_type)).sum::<f32>();
+            }
+        }
+        if norm_type == f32::INFINITY {
+            norm
+        } else {
+            norm.powf(1.0 / norm_type)
+        }
+    }
+
+    // scales all gradients so that the total norm is at most max_norm, and
+    // returns the norm before clipping
+    pub fn clip_grad_norm(&mut self, max_norm: f32) -> f32 {
+        self.clip_grad_norm_with(max_norm, 2.0)
+    }
+
+    // clip_grad_norm with the p-norm of grad_norm_with
+    pub fn clip_grad_norm_with(&mut self, max_norm: f32, norm_type: f32) -> f32 {
+        assert!(max_norm >= 0.0);
+        let norm = self.grad_norm_with(norm_type);
+        let scale = max_norm / (norm + 1e-6);
+        if scale < 1.0 {
+            for param in self.params() {
+                param.borrow_mut().grad.iter_mut().for_each(|g| *g *= scale);
+            }
+        }
+        norm
+    }
+
+    // clamps each gradient to [-clip_value, clip_value], and returns the
+    // total norm before clipping
+    pub fn clip_grad_value(&mut self, clip_value: f32) -> f32 {
+        assert!(clip_value >= 0.0);
+        let norm = self.grad_norm();
+        for param in self.params() {
+            let mut param = param.borrow_mut();
+            param
+                .grad
+                .iter_mut()
+                .for_each(|g| *g = g.clamp(-clip_value, clip_value));
+        }
+        norm
+    }
+
+    // scales gradients of each parameter so that its norm is at most
+    // max_norm, and returns the total norm before clipping
+    pub fn clip_grad_norm_per_param(&mut self, max_norm: f32) -> f32 {
+        assert!(max_norm >= 0.0);
+        let norm = self.grad_norm();
+        for param in self.params() {
+            let mut param = param.borrow_mut();
+            let param_norm = param.grad.iter().map(|g| g * g).sum::<f32>().sqrt();
+            let scale = max_norm / (param_norm + 1e-6);
+            if scale < 1.0 {
+                param.grad.iter_mut().for_each(|g| *g *= scale);
+            }
+        }
+        norm
+    }
+
+    // states of the default group are stored without prefix and those of
+    // group i under `param_groups.{i}.`
+    pub fn state_dict(&self) -> BTreeMap<String, Rc<RefCell<Variable>>> {
+        let mut state_dict = BTreeMap::new();
+        for (i, group) in self.groups.iter().enumerate() {
+            for (key, state) in group.optimizer_impl.state_dict() {
+                state_dict.insert(group_key(i, key), Rc::new(RefCell::new(state)));
+            }
+        }
+        state_dict
+    }
+
+    // the optimizer type and integers of each group prefixed as in state_dict
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
+        let name = self.groups[0].optimizer_impl.name();
+        metadata.insert(TYPE_KEY.to_string(), name.to_string());
+        for (i, group) in self.groups.iter().enumerate() {
+            for (key, value) in group.optimizer_impl.metadata() {
+                metadata.insert(group_key(i, key), value);
+            }
+        }
+        metadata
+    }
+
+    pub fn save_state_dict(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        native::save_with_metadata(path, &self.state_dict(), &self.metadata())
+    }
+
+    // states are restored for groups and parameters given in the same order
+    pub fn load_state_dict(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (state_dict, mut metadata) = native::load_with_metadata(path)?;
+
+        // states of another optimizer are rejected even if keys happen to match
+        let name = metadata
+            .remove(TYPE_KEY)
+            .ok_or_else(|| StateDictError::MissingKeys(vec![TYPE_KEY.to_string()]))?;
+        let expected = self.groups[0].optimizer_impl.name();
+        if name != expected {
+            return Err(Box::new(StateDictError::TypeMismatch {
+                expected: expected.to_string(),
+                actual: name,
+            }));
+        }
+
+        // split states by group
+        let mut unexpected = vec![];
+        let group_state_dicts = split_groups(state_dict, self.groups.len(), &mut unexpected);
+        let group_metadata = split_groups(metadata, self.groups.len(), &mut unexpected);
+        if !unexpected.is_empty() {
+            return Err(Box::new(StateDictError::UnexpectedKeys(unexpected)));
+        }
+
+        // every group is loaded before any is replaced
+        let mut loaded = vec![];
+        for (i, group) in self.groups.iter().enumerate() {
+            let param_sizes: Vec<usize> = group.params.iter().map(|p| p.borrow().size()).collect();
+            let mut optimizer_impl = group.optimizer_impl.new_group();
+            optimizer_impl.load_state_dict(
+                &group_state_dicts[i],
+                &group_metadata[i],
+                &param_sizes,
+            )?;
+            loaded.push(optimizer_impl);
+        }
+        for (group, optimizer_impl) in self.groups.iter_mut().zip(loaded) {
+            group.optimizer_impl = optimizer_impl;
+        }
+        Ok(())
+    }
 }
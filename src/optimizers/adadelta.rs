@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::optimizer::{
+    check_param_states, get_param_states, get_state, insert_param_states, scalar_state,
+    unknown_option, OptimizerImpl, OptionError, OptionValue,
+};
+use crate::serialization::StateDictError;
+use crate::variable::Variable;
+
+pub struct Adadelta {
+    pub lr: f32,
+    // smoothing constant of squared gradients and updates
+    pub rho: f32,
+    pub eps: f32,
+    pub weight_decay: f32,
+    square_avgs: Vec<Vec<f32>>,
+    delta_avgs: Vec<Vec<f32>>,
+}
+
+impl Adadelta {
+    pub fn new(lr: f32, rho: f32, eps: f32, weight_decay: f32) -> Self {
+        Self {
+            lr,
+            rho,
+            eps,
+            weight_decay,
+            square_avgs: vec![],
+            delta_avgs: vec![],
+        }
+    }
+}
+
+impl OptimizerImpl for Adadelta {
+    fn name(&self) -> &str {
+        "Adadelta"
+    }
+
+    fn update(&mut self, params: &[Rc<RefCell<Variable>>]) {
+        if self.square_avgs.is_empty() {
+            for param in params {
+                let state = vec![0.0; param.borrow().size()];
+                self.square_avgs.push(state.clone());
+                self.delta_avgs.push(state);
+            }
+        }
+        assert_eq!(self.square_avgs.len(), params.len());
+
+        for (i, param) in params.iter().enumerate() {
+            let mut param = param.borrow_mut();
+            for j in 0..param.size() {
+                let grad = param.grad[j] + self.weight_decay * param.data[j];
+
+                // steps are scaled by the ratio of RMS of updates and gradients
+                let square_avg = &mut self.square_avgs[i][j];
+                *square_avg = self.rho * *square_avg + (1.0 - self.rho) * grad * grad;
+                let delta_avg = &mut self.delta_avgs[i][j];
+                let delta = (*delta_avg + self.eps).sqrt() / (*square_avg + self.eps).sqrt() * grad;
+                *delta_avg = self.rho * *delta_avg + (1.0 - self.rho) * delta * delta;
+
+                param.data[j] -= self.lr * delta;
+            }
+        }
+    }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f32) {
+        self.lr = lr;
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        match name {
+            "lr" => self.lr = value.float(name)?,
+            "rho" => self.rho = value.float(name)?,
+            "eps" => self.eps = value.float(name)?,
+            "weight_decay" => self.weight_decay = value.float(name)?,
+            _ => return Err(unknown_option(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    fn new_group(&self) -> Box<dyn OptimizerImpl> {
+        Box::new(Self::new(self.lr, self.rho, self.eps, self.weight_decay))
+    }
+
+    fn state_dict(&self) -> BTreeMap<String, Variable> {
+        let mut state_dict = BTreeMap::new();
+        state_dict.insert("lr".to_string(), scalar_state(self.lr));
+        state_dict.insert("rho".to_string(), scalar_state(self.rho));
+        state_dict.insert("eps".to_string(), scalar_state(self.eps));
+        state_dict.insert("weight_decay".to_string(), scalar_state(self.weight_decay));
+        insert_param_states(&mut state_dict, "square_avgs", &self.square_avgs);
+        insert_param_states(&mut state_dict, "delta_avgs", &self.delta_avgs);
+        state_dict
+    }
+
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        _metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError> {
+        let lr = get_state(state_dict, "lr", &[1])?.data[0];
+        let rho = get_state(state_dict, "rho", &[1])?.data[0];
+        let eps = get_state(state_dict, "eps", &[1])?.data[0];
+        let weight_decay = get_state(state_dict, "weight_decay", &[1])?.data[0];
+        let square_avgs = get_param_states(state_dict, "square_avgs", param_sizes)?;
+        let delta_avgs = get_param_states(state_dict, "delta_avgs", param_sizes)?;
+        check_param_states("delta_avgs", &delta_avgs, square_avgs.len())?;
+
+        self.lr = lr;
+        self.rho = rho;
+        self.eps = eps;
+        self.weight_decay = weight_decay;
+        self.square_avgs = square_avgs;
+        self.delta_avgs = delta_avgs;
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::optimizer::{
+    get_param_states, get_state, get_step_count, insert_param_states, scalar_state, unknown_option,
+    OptimizerImpl, OptionError, OptionValue,
+};
+use crate::serialization::StateDictError;
+use crate::variable::Variable;
+
+pub struct Adagrad {
+    pub lr: f32,
+    pub eps: f32,
+    pub lr_decay: f32,
+    pub weight_decay: f32,
+    pub initial_accumulator_value: f32,
+    sums: Vec<Vec<f32>>,
+    t: u32,
+}
+
+impl Adagrad {
+    pub fn new(
+        lr: f32,
+        eps: f32,
+        lr_decay: f32,
+        weight_decay: f32,
+        initial_accumulator_value: f32,
+    ) -> Self {
+        Self {
+            lr,
+            eps,
+            lr_decay,
+            weight_decay,
+            initial_accumulator_value,
+            sums: vec![],
+            t: 0,
+        }
+    }
+}
+
+impl OptimizerImpl for Adagrad {
+    fn name(&self) -> &str {
+        "Adagrad"
+    }
+
+    fn update(&mut self, params: &[Rc<RefCell<Variable>>]) {
+        if self.sums.is_empty() {
+            for param in params {
+                let sum = vec![self.initial_accumulator_value; param.borrow().size()];
+                self.sums.push(sum);
+            }
+        }
+        assert_eq!(self.sums.len(), params.len());
+
+        self.t += 1;
+        let lr = self.lr / (1.0 + (self.t - 1) as f32 * self.lr_decay);
+
+        for (i, param) in params.iter().enumerate() {
+            let mut param = param.borrow_mut();
+            for j in 0..param.size() {
+                let grad = param.grad[j] + self.weight_decay * param.data[j];
+                self.sums[i][j] += grad * grad;
+                param.data[j] -= lr * grad / (self.sums[i][j].sqrt() + self.eps);
+            }
+        }
+    }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f32) {
+        self.lr = lr;
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        match name {
+            "lr" => self.lr = value.float(name)?,
+            "eps" => self.eps = value.float(name)?,
+            "lr_decay" => self.lr_decay = value.float(name)?,
+            "weight_decay" => self.weight_decay = value.float(name)?,
+            "initial_accumulator_value" => self.initial_accumulator_value = value.float(name)?,
+            _ => return Err(unknown_option(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    fn new_group(&self) -> Box<dyn OptimizerImpl> {
+        Box::new(Self::new(
+            self.lr,
+            self.eps,
+            self.lr_decay,
+            self.weight_decay,
+            self.initial_accumulator_value,
+        ))
+    }
+
+    fn state_dict(&self) -> BTreeMap<String, Variable> {
+        let mut state_dict = BTreeMap::new();
+        state_dict.insert("lr".to_string(), scalar_state(self.lr));
+        state_dict.insert("eps".to_string(), scalar_state(self.eps));
+        state_dict.insert("lr_decay".to_string(), scalar_state(self.lr_decay));
+        state_dict.insert("weight_decay".to_string(), scalar_state(self.weight_decay));
+        state_dict.insert(
+            "initial_accumulator_value".to_string(),
+            scalar_state(self.initial_accumulator_value),
+        );
+        insert_param_states(&mut state_dict, "sums", &self.sums);
+        state_dict
+    }
+
+    fn metadata(&self) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
+        metadata.insert("t".to_string(), self.t.to_string());
+        metadata
+    }
+
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError> {
+        let lr = get_state(state_dict, "lr", &[1])?.data[0];
+        let eps = get_state(state_dict, "eps", &[1])?.data[0];
+        let lr_decay = get_state(state_dict, "lr_decay", &[1])?.data[0];
+        let weight_decay = get_state(state_dict, "weight_decay", &[1])?.data[0];
+        let initial_accumulator_value =
+            get_state(state_dict, "initial_accumulator_value", &[1])?.data[0];
+        let t = get_step_count(metadata, "t")?;
+        let sums = get_param_states(state_dict, "sums", param_sizes)?;
+
+        self.lr = lr;
+        self.eps = eps;
+        self.lr_decay = lr_decay;
+        self.weight_decay = weight_decay;
+        self.initial_accumulator_value = initial_accumulator_value;
+        self.t = t;
+        self.sums = sums;
+        Ok(())
+    }
+}
@@ -1,45 +1,73 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
-use crate::optimizer::OptimizerImpl;
+use crate::optimizer::{
+    check_param_states, get_param_states, get_state, get_step_count, insert_param_states,
+    scalar_state, unknown_option, zero_states, OptimizerImpl, OptionError, OptionValue,
+};
+use crate::serialization::StateDictError;
 use crate::variable::Variable;
 
 pub struct Adam {
     pub lr: f32,
     pub betas: (f32, f32),
     pub eps: f32,
+    pub weight_decay: f32,
+    // decays parameters directly instead of adding L2 penalty to gradients (AdamW)
+    pub decoupled_weight_decay: bool,
+    // normalizes with the maximum of past second moments
+    pub amsgrad: bool,
     means: Vec<Vec<f32>>,
     vars: Vec<Vec<f32>>,
+    max_vars: Vec<Vec<f32>>,
     t: u32,
 }
 
 impl Adam {
-    pub fn new(lr: f32, betas: (f32, f32), eps: f32) -> Self {
+    pub fn new(
+        lr: f32,
+        betas: (f32, f32),
+        eps: f32,
+        weight_decay: f32,
+        decoupled_weight_decay: bool,
+        amsgrad: bool,
+    ) -> Self {
         Self {
             lr,
             betas,
             eps,
+            weight_decay,
+            decoupled_weight_decay,
+            amsgrad,
             means: vec![],
             vars: vec![],
+            max_vars: vec![],
             t: 0,
         }
     }
 
+    // creates missing states, including those of options set after
+    // earlier updates
     fn init_states(&mut self, params: &[Rc<RefCell<Variable>>]) {
-        for param in params {
-            let mean = vec![0.0; param.borrow().size() as usize];
-            let var = vec![0.0; param.borrow().size() as usize];
-            self.means.push(mean);
-            self.vars.push(var);
+        if self.means.is_empty() {
+            self.means = zero_states(params);
+            self.vars = zero_states(params);
+        }
+        if self.amsgrad && self.max_vars.is_empty() {
+            self.max_vars = zero_states(params);
         }
     }
 }
 
 impl OptimizerImpl for Adam {
+    fn name(&self) -> &str {
+        "Adam"
+    }
+
     fn update(&mut self, params: &[Rc<RefCell<Variable>>]) {
-        if self.means.is_empty() {
-            self.init_states(params);
-        }
+        self.init_states(params);
+        assert_eq!(self.means.len(), params.len());
 
         self.t += 1;
         let mut bias_correction = (1.0 - self.betas.1.powf(self.t as f32)).sqrt();
@@ -48,20 +76,130 @@ impl OptimizerImpl for Adam {
 
         for (i, param) in params.iter().enumerate().take(params.len()) {
             let mut param = param.borrow_mut();
-            for j in 0..param.size() as usize {
-                let grad = param.grad[j];
+            for j in 0..param.size() {
+                let mut grad = param.grad[j];
+                if self.decoupled_weight_decay {
+                    param.data[j] -= self.lr * self.weight_decay * param.data[j];
+                } else {
+                    grad += self.weight_decay * param.data[j];
+                }
 
                 // update states
                 let old_mean = self.means[i][j];
                 let old_var = self.vars[i][j];
                 let new_mean = self.betas.0 * old_mean + (1.0 - self.betas.0) * grad;
                 let new_var = self.betas.1 * old_var + (1.0 - self.betas.1) * grad * grad;
+                let denom_var = if self.amsgrad {
+                    self.max_vars[i][j] = self.max_vars[i][j].max(new_var);
+                    self.max_vars[i][j]
+                } else {
+                    new_var
+                };
 
                 // update parameter
-                param.data[j] -= alpha * new_mean / (new_var.sqrt() + self.eps);
+                param.data[j] -= alpha * new_mean / (denom_var.sqrt() + self.eps);
                 self.means[i][j] = new_mean;
                 self.vars[i][j] = new_var;
             }
         }
     }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f32) {
+        self.lr = lr;
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        match name {
+            "lr" => self.lr = value.float(name)?,
+            "beta1" => self.betas.0 = value.float(name)?,
+            "beta2" => self.betas.1 = value.float(name)?,
+            "eps" => self.eps = value.float(name)?,
+            "weight_decay" => self.weight_decay = value.float(name)?,
+            "amsgrad" => self.amsgrad = value.bool(name)?,
+            _ => return Err(unknown_option(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    fn new_group(&self) -> Box<dyn OptimizerImpl> {
+        Box::new(Self::new(
+            self.lr,
+            self.betas,
+            self.eps,
+            self.weight_decay,
+            self.decoupled_weight_decay,
+            self.amsgrad,
+        ))
+    }
+
+    fn state_dict(&self) -> BTreeMap<String, Variable> {
+        let mut state_dict = BTreeMap::new();
+        state_dict.insert("lr".to_string(), scalar_state(self.lr));
+        let mut betas = Variable::new(vec![2]);
+        betas.set_data(&[self.betas.0, self.betas.1]);
+        state_dict.insert("betas".to_string(), betas);
+        state_dict.insert("eps".to_string(), scalar_state(self.eps));
+        state_dict.insert("weight_decay".to_string(), scalar_state(self.weight_decay));
+        state_dict.insert(
+            "decoupled_weight_decay".to_string(),
+            scalar_state(if self.decoupled_weight_decay {
+                1.0
+            } else {
+                0.0
+            }),
+        );
+        state_dict.insert(
+            "amsgrad".to_string(),
+            scalar_state(if self.amsgrad { 1.0 } else { 0.0 }),
+        );
+        insert_param_states(&mut state_dict, "means", &self.means);
+        insert_param_states(&mut state_dict, "vars", &self.vars);
+        insert_param_states(&mut state_dict, "max_vars", &self.max_vars);
+        state_dict
+    }
+
+    fn metadata(&self) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
+        metadata.insert("t".to_string(), self.t.to_string());
+        metadata
+    }
+
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError> {
+        let lr = get_state(state_dict, "lr", &[1])?.data[0];
+        let betas = &get_state(state_dict, "betas", &[2])?.data;
+        let eps = get_state(state_dict, "eps", &[1])?.data[0];
+        let weight_decay = get_state(state_dict, "weight_decay", &[1])?.data[0];
+        let decoupled_weight_decay =
+            get_state(state_dict, "decoupled_weight_decay", &[1])?.data[0] != 0.0;
+        let amsgrad = get_state(state_dict, "amsgrad", &[1])?.data[0] != 0.0;
+        let t = get_step_count(metadata, "t")?;
+        let means = get_param_states(state_dict, "means", param_sizes)?;
+        let vars = get_param_states(state_dict, "vars", param_sizes)?;
+        let max_vars = get_param_states(state_dict, "max_vars", param_sizes)?;
+        check_param_states("vars", &vars, means.len())?;
+        if amsgrad {
+            check_param_states("max_vars", &max_vars, means.len())?;
+        }
+
+        self.lr = lr;
+        self.betas = (betas[0], betas[1]);
+        self.eps = eps;
+        self.weight_decay = weight_decay;
+        self.decoupled_weight_decay = decoupled_weight_decay;
+        self.amsgrad = amsgrad;
+        self.t = t;
+        self.means = means;
+        self.vars = vars;
+        self.max_vars = max_vars;
+        Ok(())
+    }
 }
@@ -1,21 +1,129 @@
+mod adadelta;
+mod adagrad;
 mod adam;
+mod rmsprop;
 mod sgd;
 
 use crate::optimizer::Optimizer;
+pub use crate::optimizer::{OptionError, OptionValue};
 
 pub fn sgd(lr: f32) -> Box<Optimizer> {
-    let sgd_impl = Box::new(sgd::Sgd { lr });
+    sgd_with(lr, 0.0, 0.0, 0.0, false)
+}
+
+pub fn sgd_with(
+    lr: f32,
+    momentum: f32,
+    dampening: f32,
+    weight_decay: f32,
+    nesterov: bool,
+) -> Box<Optimizer> {
+    let sgd_impl = Box::new(sgd::Sgd::new(
+        lr,
+        momentum,
+        dampening,
+        weight_decay,
+        nesterov,
+    ));
     Box::new(Optimizer::new(sgd_impl))
 }
 
 pub fn adam(lr: f32, betas: (f32, f32), eps: f32) -> Box<Optimizer> {
-    let adam_impl = Box::new(adam::Adam::new(lr, betas, eps));
+    adam_with(lr, betas, eps, 0.0, false)
+}
+
+// weight decay is added to gradients as L2 penalty
+pub fn adam_with(
+    lr: f32,
+    betas: (f32, f32),
+    eps: f32,
+    weight_decay: f32,
+    amsgrad: bool,
+) -> Box<Optimizer> {
+    let adam_impl = Box::new(adam::Adam::new(
+        lr,
+        betas,
+        eps,
+        weight_decay,
+        false,
+        amsgrad,
+    ));
+    Box::new(Optimizer::new(adam_impl))
+}
+
+pub fn adamw(lr: f32, betas: (f32, f32), eps: f32, weight_decay: f32) -> Box<Optimizer> {
+    adamw_with(lr, betas, eps, weight_decay, false)
+}
+
+// weight decay is applied to parameters directly
+pub fn adamw_with(
+    lr: f32,
+    betas: (f32, f32),
+    eps: f32,
+    weight_decay: f32,
+    amsgrad: bool,
+) -> Box<Optimizer> {
+    let adam_impl = Box::new(adam::Adam::new(lr, betas, eps, weight_decay, true, amsgrad));
     Box::new(Optimizer::new(adam_impl))
 }
 
+pub fn rmsprop(lr: f32, alpha: f32, eps: f32) -> Box<Optimizer> {
+    rmsprop_with(lr, alpha, eps, 0.0, 0.0, false)
+}
+
+pub fn rmsprop_with(
+    lr: f32,
+    alpha: f32,
+    eps: f32,
+    weight_decay: f32,
+    momentum: f32,
+    centered: bool,
+) -> Box<Optimizer> {
+    let rmsprop_impl = Box::new(rmsprop::RmsProp::new(
+        lr,
+        alpha,
+        eps,
+        weight_decay,
+        momentum,
+        centered,
+    ));
+    Box::new(Optimizer::new(rmsprop_impl))
+}
+
+pub fn adagrad(lr: f32, eps: f32) -> Box<Optimizer> {
+    adagrad_with(lr, eps, 0.0, 0.0, 0.0)
+}
+
+pub fn adagrad_with(
+    lr: f32,
+    eps: f32,
+    lr_decay: f32,
+    weight_decay: f32,
+    initial_accumulator_value: f32,
+) -> Box<Optimizer> {
+    let adagrad_impl = Box::new(adagrad::Adagrad::new(
+        lr,
+        eps,
+        lr_decay,
+        weight_decay,
+        initial_accumulator_value,
+    ));
+    Box::new(Optimizer::new(adagrad_impl))
+}
+
+pub fn adadelta(lr: f32, rho: f32, eps: f32) -> Box<Optimizer> {
+    adadelta_with(lr, rho, eps, 0.0)
+}
+
+pub fn adadelta_with(lr: f32, rho: f32, eps: f32, weight_decay: f32) -> Box<Optimizer> {
+    let adadelta_impl = Box::new(adadelta::Adadelta::new(lr, rho, eps, weight_decay));
+    Box::new(Optimizer::new(adadelta_impl))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::serialization::{native, temp_path, StateDictError};
     use crate::variable::Variable;
     use std::cell::RefCell;
     use std::rc::Rc;
@@ -32,6 +140,60 @@ mod tests {
         optim.update();
     }
 
+    // runs steps from x = 1 with the given gradients
+    fn steps(optim: &mut Optimizer, grads: &[f32]) -> Vec<f32> {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1])));
+        x.borrow_mut().set_data(&[1.0]);
+        optim.set_params(vec![x.clone()]);
+
+        let mut values = vec![];
+        for grad in grads.iter() {
+            x.borrow_mut().set_grad(&[*grad]);
+            optim.update();
+            values.push(x.borrow().data[0]);
+        }
+        values
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn sgd_momentum() {
+        // buf = 1, 0.9 * 1 + 1
+        assert_close(
+            &steps(&mut sgd_with(0.1, 0.9, 0.0, 0.0, false), &[1.0, 1.0]),
+            &[0.9, 0.71],
+        );
+
+        // buf = 1, 0.9 * 1 + 0.5 * 1
+        assert_close(
+            &steps(&mut sgd_with(0.1, 0.9, 0.5, 0.0, false), &[1.0, 1.0]),
+            &[0.9, 0.76],
+        );
+    }
+
+    #[test]
+    fn sgd_nesterov() {
+        // g = 1 + 0.9 * 1, 1 + 0.9 * 1.9
+        assert_close(
+            &steps(&mut sgd_with(0.1, 0.9, 0.0, 0.0, true), &[1.0, 1.0]),
+            &[0.81, 0.539],
+        );
+    }
+
+    #[test]
+    fn sgd_weight_decay() {
+        // g = 1 + 0.1 * 1, 1 + 0.1 * 0.89
+        assert_close(
+            &steps(&mut sgd_with(0.1, 0.0, 0.0, 0.1, false), &[1.0, 1.0]),
+            &[0.89, 0.7811],
+        );
+    }
+
     #[test]
     fn adam_update() {
         let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
@@ -43,4 +205,439 @@ mod tests {
         optim.zero_grad();
         optim.update();
     }
+
+    #[test]
+    fn adam_weight_decay() {
+        // a constant gradient moves by lr after bias correction
+        assert_close(
+            &steps(&mut adamw(0.1, (0.9, 0.999), 1e-8, 0.1), &[1.0, 1.0]),
+            &[0.89, 0.7811],
+        );
+        assert_close(
+            &steps(
+                &mut adam_with(0.1, (0.9, 0.999), 1e-8, 0.1, false),
+                &[1.0, 1.0],
+            ),
+            &[0.9, 0.8000249],
+        );
+    }
+
+    #[test]
+    fn adam_amsgrad() {
+        // the second moment decreases after the first step
+        let grads = [1.0, 0.1];
+        assert_close(
+            &steps(&mut adam_with(0.1, (0.9, 0.5), 1e-8, 0.0, true), &grads),
+            &[0.9, 0.8355397],
+        );
+        assert_close(
+            &steps(&mut adam(0.1, (0.9, 0.5), 1e-8), &grads),
+            &[0.9, 0.8097376],
+        );
+    }
+
+    #[test]
+    fn rmsprop_update() {
+        assert_close(
+            &steps(&mut rmsprop(0.01, 0.99, 1e-8), &[1.0, 1.0]),
+            &[0.9, 0.8291119],
+        );
+        assert_close(
+            &steps(
+                &mut rmsprop_with(0.01, 0.9, 1e-8, 0.0, 0.9, true),
+                &[1.0, 0.5],
+            ),
+            &[0.9666667, 0.9204786],
+        );
+    }
+
+    #[test]
+    fn adagrad_update() {
+        // lr / sqrt(1), lr / sqrt(2)
+        assert_close(
+            &steps(&mut adagrad(0.1, 1e-10), &[1.0, 1.0]),
+            &[0.9, 0.8292893],
+        );
+        assert_close(
+            &steps(&mut adagrad_with(0.1, 1e-10, 0.5, 0.1, 0.1), &[1.0, 1.0]),
+            &[0.9038926, 0.857908],
+        );
+    }
+
+    #[test]
+    fn adadelta_update() {
+        assert_close(
+            &steps(&mut adadelta(1.0, 0.9, 1e-6), &[1.0, 1.0]),
+            &[0.9968377, 0.9935933],
+        );
+        assert_close(
+            &steps(&mut adadelta_with(1.0, 0.9, 1e-6, 0.1), &[1.0, 1.0]),
+            &[0.9968377, 0.9935938],
+        );
+    }
+
+    fn clip_params() -> (Rc<RefCell<Variable>>, Rc<RefCell<Variable>>) {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![1])));
+        x.borrow_mut().set_grad(&[3.0, -4.0]);
+        y.borrow_mut().set_grad(&[12.0]);
+        (x, y)
+    }
+
+    #[test]
+    fn clip_grad_norm() {
+        let (x, y) = clip_params();
+        let mut optim = sgd(0.1);
+        optim.set_params(vec![x.clone(), y.clone()]);
+
+        // total norm is sqrt(9 + 16 + 144)
+        assert!((optim.clip_grad_norm(6.5) - 13.0).abs() < 1e-5);
+        assert_close(&x.borrow().grad, &[1.5, -2.0]);
+        assert_close(&y.borrow().grad, &[6.0]);
+        assert!((optim.grad_norm() - 6.5).abs() < 1e-5);
+
+        // gradients within the limit are kept
+        assert!((optim.clip_grad_norm(10.0) - 6.5).abs() < 1e-5);
+        assert_close(&y.borrow().grad, &[6.0]);
+    }
+
+    #[test]
+    fn clip_grad_norm_inf() {
+        let (x, y) = clip_params();
+        let mut optim = sgd(0.1);
+        optim.set_params(vec![x.clone(), y.clone()]);
+
+        // the infinity norm is the largest absolute gradient
+        assert!((optim.clip_grad_norm_with(6.0, f32::INFINITY) - 12.0).abs() < 1e-5);
+        assert_close(&x.borrow().grad, &[1.5, -2.0]);
+        assert_close(&y.borrow().grad, &[6.0]);
+        assert!((optim.grad_norm_with(f32::INFINITY) - 6.0).abs() < 1e-5);
+
+        // the 1-norm sums absolute gradients
+        assert!((optim.grad_norm_with(1.0) - 9.5).abs() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn clip_grad_norm_negative() {
+        let (x, _) = clip_params();
+        let mut optim = sgd(0.1);
+        optim.set_params(vec![x]);
+        optim.clip_grad_norm(-1.0);
+    }
+
+    #[test]
+    fn clip_grad_value() {
+        let (x, y) = clip_params();
+        let mut optim = sgd(0.1);
+        optim.set_params(vec![x.clone(), y.clone()]);
+
+        assert!((optim.clip_grad_value(3.5) - 13.0).abs() < 1e-5);
+        assert_eq!(x.borrow().grad, vec![3.0, -3.5]);
+        assert_eq!(y.borrow().grad, vec![3.5]);
+    }
+
+    #[test]
+    fn clip_grad_norm_per_param() {
+        let (x, y) = clip_params();
+        let mut optim = sgd(0.1);
+        optim.set_params(vec![x.clone(), y.clone()]);
+
+        // norms are 5 and 12
+        assert!((optim.clip_grad_norm_per_param(6.0) - 13.0).abs() < 1e-5);
+        assert_close(&x.borrow().grad, &[3.0, -4.0]);
+        assert_close(&y.borrow().grad, &[6.0]);
+    }
+
+    #[test]
+    fn param_groups() {
+        let weight = Rc::new(RefCell::new(Variable::new(vec![1])));
+        let bias = Rc::new(RefCell::new(Variable::new(vec![1])));
+        for param in [&weight, &bias] {
+            param.borrow_mut().set_data(&[1.0]);
+            param.borrow_mut().set_grad(&[1.0]);
+        }
+
+        // no weight decay and a lower learning rate for biases
+        let mut optim = sgd_with(0.1, 0.0, 0.0, 0.1, false);
+        optim.set_params(vec![weight.clone()]);
+        let group = optim
+            .add_param_group(
+                vec![bias.clone()],
+                &[("lr", 0.01.into()), ("weight_decay", 0.0.into())],
+            )
+            .unwrap();
+        assert_eq!(group, 1);
+        assert_eq!(optim.num_groups(), 2);
+        optim.update();
+        assert_close(&weight.borrow().data, &[0.89]);
+        assert_close(&bias.borrow().data, &[0.99]);
+
+        // states are kept per group
+        let mut optim = adamw(0.1, (0.9, 0.999), 1e-8, 0.0);
+        optim
+            .add_param_group(
+                vec![weight.clone()],
+                &[("weight_decay", 0.1.into()), ("amsgrad", true.into())],
+            )
+            .unwrap();
+        optim.set_params(vec![bias.clone()]);
+        optim.update();
+        assert_close(&weight.borrow().data, &[0.89 * 0.99 - 0.1]);
+        assert_close(&bias.borrow().data, &[0.89]);
+        let state_dict = optim.state_dict();
+        assert_eq!(state_dict["weight_decay"].borrow().data, vec![0.0]);
+        assert_eq!(
+            state_dict["param_groups.1.weight_decay"].borrow().data,
+            vec![0.1]
+        );
+        assert!(state_dict.contains_key("param_groups.1.max_vars.0"));
+
+        optim.set_lr(0.5);
+        assert_eq!(optim.group_lr(1), 0.5);
+    }
+
+    #[test]
+    fn param_group_invalid_options() {
+        let mut optim = sgd(0.1);
+        assert_eq!(
+            optim.add_param_group(vec![], &[("betas", 0.9.into())]),
+            Err(OptionError::UnknownOption {
+                optimizer: "Sgd".to_string(),
+                name: "betas".to_string(),
+            })
+        );
+        assert_eq!(
+            optim.add_param_group(vec![], &[("nesterov", 1.0.into())]),
+            Err(OptionError::TypeMismatch {
+                name: "nesterov".to_string(),
+                expected: "bool",
+            })
+        );
+        assert!(optim
+            .add_param_group(vec![], &[("nesterov", true.into())])
+            .is_err());
+        assert_eq!(optim.num_groups(), 1);
+
+        // nesterov is checked after all options are set
+        let group = optim.add_param_group(
+            vec![],
+            &[("nesterov", true.into()), ("momentum", 0.9.into())],
+        );
+        assert_eq!(group, Ok(1));
+    }
+
+    #[test]
+    fn set_group_option_after_update() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_grad(&[1.0, 2.0]);
+
+        // states of options enabled later are created on the next update
+        let optims = [
+            (rmsprop(0.1, 0.99, 1e-8), "centered", true.into()),
+            (rmsprop(0.1, 0.99, 1e-8), "momentum", 0.9.into()),
+            (adam(0.1, (0.9, 0.999), 1e-8), "amsgrad", true.into()),
+            (sgd(0.1), "momentum", 0.9.into()),
+        ];
+        for (mut optim, name, value) in optims {
+            optim.set_params(vec![x.clone()]);
+            optim.update();
+            optim.set_group_option(0, name, value).unwrap();
+            optim.update();
+            assert!(x.borrow().data.iter().all(|v| v.is_finite()));
+        }
+
+        // invalid options leave the group unchanged
+        let mut optim = sgd(0.1);
+        assert!(optim.set_group_option(0, "nesterov", true.into()).is_err());
+        optim.set_group_option(0, "momentum", 0.9.into()).unwrap();
+        optim.set_group_option(0, "nesterov", true.into()).unwrap();
+        assert_eq!(optim.state_dict()["nesterov"].borrow().data, vec![1.0]);
+    }
+
+    #[test]
+    fn param_groups_state_dict() {
+        let path = temp_path("groups.bin");
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_grad(&[1.0, 2.0]);
+
+        let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+        optim
+            .add_param_group(vec![x.clone()], &[("lr", 0.01.into())])
+            .unwrap();
+        optim.update();
+        optim.save_state_dict(&path).unwrap();
+
+        let mut resumed = adam(0.1, (0.9, 0.999), 1e-8);
+        resumed
+            .add_param_group(vec![x.clone()], &[("lr", 0.5.into())])
+            .unwrap();
+        resumed.load_state_dict(&path).unwrap();
+        assert_eq!(resumed.group_lr(1), 0.01);
+
+        // groups missing in the optimizer are rejected
+        let mut other = adam(0.1, (0.9, 0.999), 1e-8);
+        assert!(other.load_state_dict(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn adam_resume() {
+        let path = temp_path("adam.bin");
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 3])));
+        x.borrow_mut().set_grad(&[0.1, -0.2, 0.3, -0.4, 0.5, -0.6]);
+        let y = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        y.borrow_mut().set_data(&x.borrow().data.clone());
+        y.borrow_mut().set_grad(&x.borrow().grad.clone());
+
+        // two steps in one run
+        let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+        optim.set_params(vec![x.clone()]);
+        optim.update();
+        optim.save_state_dict(&path).unwrap();
+        optim.update();
+
+        // one step, then resume with different hyperparameters overwritten
+        let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+        optim.set_params(vec![y.clone()]);
+        optim.update();
+        let mut resumed = adam(0.5, (0.5, 0.5), 1.0);
+        resumed.set_params(vec![y.clone()]);
+        resumed.load_state_dict(&path).unwrap();
+        resumed.update();
+
+        assert_eq!(x.borrow().data, y.borrow().data);
+        let state_dict = resumed.state_dict();
+        assert_eq!(resumed.metadata()["t"], "2");
+        assert_eq!(state_dict["betas"].borrow().data, vec![0.9, 0.999]);
+        assert_eq!(state_dict["means.0"].borrow().shape, vec![6]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sgd_state_dict() {
+        let path = temp_path("sgd.bin");
+        sgd(0.01).save_state_dict(&path).unwrap();
+        let mut optim = sgd(0.1);
+        optim.load_state_dict(&path).unwrap();
+        assert_eq!(optim.state_dict()["lr"].borrow().data, vec![0.01]);
+
+        // states of another optimizer are rejected
+        sgd(0.1).save_state_dict(&path).unwrap();
+        let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+        assert!(optim.load_state_dict(&path).is_err());
+
+        // even if the keys needed by the optimizer are present
+        adam(0.1, (0.9, 0.999), 1e-8)
+            .save_state_dict(&path)
+            .unwrap();
+        let error = sgd(0.1).load_state_dict(&path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "type mismatch: expected Sgd but got Adam"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn state_dict_param_mismatch() {
+        let path = temp_path("param_mismatch.bin");
+        let x = Rc::new(RefCell::new(Variable::new(vec![2, 3])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![4])));
+        let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+        optim.set_params(vec![x.clone()]);
+        optim.update();
+        optim.save_state_dict(&path).unwrap();
+
+        // states of a different model are rejected instead of panicking in update
+        let load = |params: Vec<Rc<RefCell<Variable>>>| {
+            let mut optim = adam(0.1, (0.9, 0.999), 1e-8);
+            optim.set_params(params);
+            let error = optim.load_state_dict(&path).unwrap_err();
+            error.downcast::<StateDictError>().map(|e| *e).unwrap()
+        };
+        assert_eq!(
+            load(vec![y.clone()]),
+            StateDictError::ShapeMismatch {
+                key: "means.0".to_string(),
+                expected: vec![4],
+                actual: vec![6],
+            }
+        );
+        assert_eq!(
+            load(vec![x.clone(), y]),
+            StateDictError::MissingKeys(vec!["means.1".to_string()])
+        );
+        assert_eq!(
+            load(vec![]),
+            StateDictError::UnexpectedKeys(vec!["means.0".to_string()])
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn state_dict_dependent_states() {
+        let path = temp_path("dependent_states.bin");
+        let x = Rc::new(RefCell::new(Variable::new(vec![2])));
+        x.borrow_mut().set_grad(&[1.0, 2.0]);
+
+        // states needed by update must all be present, and nothing is
+        // loaded otherwise
+        let check = |optim: &mut Optimizer, key: &str| {
+            optim.set_params(vec![x.clone()]);
+            optim.update();
+            let mut state_dict = optim.state_dict();
+            state_dict["lr"].borrow_mut().set_data(&[0.5]);
+            state_dict.remove(key);
+            native::save_with_metadata(&path, &state_dict, &optim.metadata()).unwrap();
+
+            let error = optim.load_state_dict(&path).unwrap_err();
+            assert_eq!(
+                *error.downcast::<StateDictError>().unwrap(),
+                StateDictError::MissingKeys(vec![key.to_string()])
+            );
+            assert_eq!(optim.state_dict()["lr"].borrow().data, vec![0.1]);
+        };
+        check(
+            &mut rmsprop_with(0.1, 0.99, 1e-8, 0.0, 0.0, true),
+            "grad_avgs.0",
+        );
+        check(
+            &mut rmsprop_with(0.1, 0.99, 1e-8, 0.0, 0.9, false),
+            "momentum_buffers.0",
+        );
+        check(&mut adadelta(0.1, 0.9, 1e-6), "delta_avgs.0");
+        check(
+            &mut adam_with(0.1, (0.9, 0.999), 1e-8, 0.0, true),
+            "max_vars.0",
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn state_dict_step_count() {
+        let path = temp_path("step_count.bin");
+        let mut optim = adagrad(0.1, 1e-10);
+        let state_dict = optim.state_dict();
+        let mut metadata = optim.metadata();
+
+        // counts beyond 2^24 are not exact in f32
+        metadata.insert("t".to_string(), "16777217".to_string());
+        native::save_with_metadata(&path, &state_dict, &metadata).unwrap();
+        optim.load_state_dict(&path).unwrap();
+        assert_eq!(optim.metadata()["t"], "16777217");
+
+        metadata.insert("t".to_string(), "0.5".to_string());
+        native::save_with_metadata(&path, &state_dict, &metadata).unwrap();
+        assert!(optim.load_state_dict(&path).is_err());
+
+        // the type is metadata, so a plain state dict has none
+        native::save(&path, &state_dict).unwrap();
+        let error = optim.load_state_dict(&path).unwrap_err();
+        assert_eq!(
+            *error.downcast::<StateDictError>().unwrap(),
+            StateDictError::MissingKeys(vec!["type".to_string()])
+        );
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::optimizer::{
+    check_param_states, get_param_states, get_state, insert_param_states, scalar_state,
+    unknown_option, zero_states, OptimizerImpl, OptionError, OptionValue,
+};
+use crate::serialization::StateDictError;
+use crate::variable::Variable;
+
+pub struct RmsProp {
+    pub lr: f32,
+    // smoothing constant of squared gradients
+    pub alpha: f32,
+    pub eps: f32,
+    pub weight_decay: f32,
+    pub momentum: f32,
+    // normalizes with the estimated variance instead of the second moment
+    pub centered: bool,
+    square_avgs: Vec<Vec<f32>>,
+    grad_avgs: Vec<Vec<f32>>,
+    momentum_buffers: Vec<Vec<f32>>,
+}
+
+impl RmsProp {
+    pub fn new(
+        lr: f32,
+        alpha: f32,
+        eps: f32,
+        weight_decay: f32,
+        momentum: f32,
+        centered: bool,
+    ) -> Self {
+        Self {
+            lr,
+            alpha,
+            eps,
+            weight_decay,
+            momentum,
+            centered,
+            square_avgs: vec![],
+            grad_avgs: vec![],
+            momentum_buffers: vec![],
+        }
+    }
+
+    // creates missing states, including those of options set after
+    // earlier updates
+    fn init_states(&mut self, params: &[Rc<RefCell<Variable>>]) {
+        if self.square_avgs.is_empty() {
+            self.square_avgs = zero_states(params);
+        }
+        if self.centered && self.grad_avgs.is_empty() {
+            self.grad_avgs = zero_states(params);
+        }
+        if self.momentum > 0.0 && self.momentum_buffers.is_empty() {
+            self.momentum_buffers = zero_states(params);
+        }
+    }
+}
+
+impl OptimizerImpl for RmsProp {
+    fn name(&self) -> &str {
+        "RmsProp"
+    }
+
+    fn update(&mut self, params: &[Rc<RefCell<Variable>>]) {
+        self.init_states(params);
+        assert_eq!(self.square_avgs.len(), params.len());
+
+        for (i, param) in params.iter().enumerate() {
+            let mut param = param.borrow_mut();
+            for j in 0..param.size() {
+                let grad = param.grad[j] + self.weight_decay * param.data[j];
+
+                // update states
+                let square_avg = &mut self.square_avgs[i][j];
+                *square_avg = self.alpha * *square_avg + (1.0 - self.alpha) * grad * grad;
+                let mut var = *square_avg;
+                if self.centered {
+                    let grad_avg = &mut self.grad_avgs[i][j];
+                    *grad_avg = self.alpha * *grad_avg + (1.0 - self.alpha) * grad;
+                    var -= *grad_avg * *grad_avg;
+                }
+                let step = grad / (var.sqrt() + self.eps);
+
+                // update parameter
+                if self.momentum > 0.0 {
+                    let buffer = &mut self.momentum_buffers[i][j];
+                    *buffer = self.momentum * *buffer + step;
+                    param.data[j] -= self.lr * *buffer;
+                } else {
+                    param.data[j] -= self.lr * step;
+                }
+            }
+        }
+    }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f32) {
+        self.lr = lr;
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        match name {
+            "lr" => self.lr = value.float(name)?,
+            "alpha" => self.alpha = value.float(name)?,
+            "eps" => self.eps = value.float(name)?,
+            "weight_decay" => self.weight_decay = value.float(name)?,
+            "momentum" => self.momentum = value.float(name)?,
+            "centered" => self.centered = value.bool(name)?,
+            _ => return Err(unknown_option(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    fn new_group(&self) -> Box<dyn OptimizerImpl> {
+        Box::new(Self::new(
+            self.lr,
+            self.alpha,
+            self.eps,
+            self.weight_decay,
+            self.momentum,
+            self.centered,
+        ))
+    }
+
+    fn state_dict(&self) -> BTreeMap<String, Variable> {
+        let mut state_dict = BTreeMap::new();
+        state_dict.insert("lr".to_string(), scalar_state(self.lr));
+        state_dict.insert("alpha".to_string(), scalar_state(self.alpha));
+        state_dict.insert("eps".to_string(), scalar_state(self.eps));
+        state_dict.insert("weight_decay".to_string(), scalar_state(self.weight_decay));
+        state_dict.insert("momentum".to_string(), scalar_state(self.momentum));
+        state_dict.insert(
+            "centered".to_string(),
+            scalar_state(if self.centered { 1.0 } else { 0.0 }),
+        );
+        insert_param_states(&mut state_dict, "square_avgs", &self.square_avgs);
+        insert_param_states(&mut state_dict, "grad_avgs", &self.grad_avgs);
+        insert_param_states(&mut state_dict, "momentum_buffers", &self.momentum_buffers);
+        state_dict
+    }
+
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        _metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError> {
+        let lr = get_state(state_dict, "lr", &[1])?.data[0];
+        let alpha = get_state(state_dict, "alpha", &[1])?.data[0];
+        let eps = get_state(state_dict, "eps", &[1])?.data[0];
+        let weight_decay = get_state(state_dict, "weight_decay", &[1])?.data[0];
+        let momentum = get_state(state_dict, "momentum", &[1])?.data[0];
+        let centered = get_state(state_dict, "centered", &[1])?.data[0] != 0.0;
+        let square_avgs = get_param_states(state_dict, "square_avgs", param_sizes)?;
+        let grad_avgs = get_param_states(state_dict, "grad_avgs", param_sizes)?;
+        let momentum_buffers = get_param_states(state_dict, "momentum_buffers", param_sizes)?;
+        if centered {
+            check_param_states("grad_avgs", &grad_avgs, square_avgs.len())?;
+        }
+        if momentum > 0.0 {
+            check_param_states("momentum_buffers", &momentum_buffers, square_avgs.len())?;
+        }
+
+        self.lr = lr;
+        self.alpha = alpha;
+        self.eps = eps;
+        self.weight_decay = weight_decay;
+        self.momentum = momentum;
+        self.centered = centered;
+        self.square_avgs = square_avgs;
+        self.grad_avgs = grad_avgs;
+        self.momentum_buffers = momentum_buffers;
+        Ok(())
+    }
+}
@@ -1,20 +1,150 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
-use crate::optimizer::OptimizerImpl;
+use crate::optimizer::{
+    get_param_states, get_state, insert_param_states, scalar_state, unknown_option, OptimizerImpl,
+    OptionError, OptionValue,
+};
+use crate::serialization::StateDictError;
 use crate::variable::Variable;
 
 pub struct Sgd {
     pub lr: f32,
+    pub momentum: f32,
+    pub dampening: f32,
+    pub weight_decay: f32,
+    pub nesterov: bool,
+    momentum_buffers: Vec<Vec<f32>>,
+}
+
+impl Sgd {
+    pub fn new(lr: f32, momentum: f32, dampening: f32, weight_decay: f32, nesterov: bool) -> Self {
+        // nesterov momentum requires momentum without dampening
+        assert!(!nesterov || (momentum > 0.0 && dampening == 0.0));
+        Self {
+            lr,
+            momentum,
+            dampening,
+            weight_decay,
+            nesterov,
+            momentum_buffers: vec![],
+        }
+    }
 }
 
 impl OptimizerImpl for Sgd {
+    fn name(&self) -> &str {
+        "Sgd"
+    }
+
     fn update(&mut self, params: &[Rc<RefCell<Variable>>]) {
-        for param in params {
+        // buffers are initialized with the first gradients
+        let first_step = self.momentum != 0.0 && self.momentum_buffers.is_empty();
+        if first_step {
+            for param in params {
+                self.momentum_buffers.push(param.borrow().grad.clone());
+            }
+        }
+
+        for (i, param) in params.iter().enumerate() {
             let mut param = param.borrow_mut();
-            for j in 0..param.size() as usize {
-                param.data[j] -= self.lr * param.grad[j];
+            for j in 0..param.size() {
+                // L2 penalty
+                let mut grad = param.grad[j] + self.weight_decay * param.data[j];
+
+                if self.momentum != 0.0 {
+                    let buffer = &mut self.momentum_buffers[i][j];
+                    if first_step {
+                        *buffer = grad;
+                    } else {
+                        *buffer = self.momentum * *buffer + (1.0 - self.dampening) * grad;
+                    }
+                    if self.nesterov {
+                        grad += self.momentum * *buffer;
+                    } else {
+                        grad = *buffer;
+                    }
+                }
+
+                param.data[j] -= self.lr * grad;
             }
         }
     }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn set_lr(&mut self, lr: f32) {
+        self.lr = lr;
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        match name {
+            "lr" => self.lr = value.float(name)?,
+            "momentum" => self.momentum = value.float(name)?,
+            "dampening" => self.dampening = value.float(name)?,
+            "weight_decay" => self.weight_decay = value.float(name)?,
+            "nesterov" => self.nesterov = value.bool(name)?,
+            _ => return Err(unknown_option(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    fn validate_options(&self) -> Result<(), OptionError> {
+        // nesterov momentum requires momentum without dampening
+        if self.nesterov && (self.momentum <= 0.0 || self.dampening != 0.0) {
+            return Err(OptionError::InvalidOptions(
+                "nesterov requires momentum and zero dampening".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn new_group(&self) -> Box<dyn OptimizerImpl> {
+        Box::new(Self::new(
+            self.lr,
+            self.momentum,
+            self.dampening,
+            self.weight_decay,
+            self.nesterov,
+        ))
+    }
+
+    fn state_dict(&self) -> BTreeMap<String, Variable> {
+        let mut state_dict = BTreeMap::new();
+        state_dict.insert("lr".to_string(), scalar_state(self.lr));
+        state_dict.insert("momentum".to_string(), scalar_state(self.momentum));
+        state_dict.insert("dampening".to_string(), scalar_state(self.dampening));
+        state_dict.insert("weight_decay".to_string(), scalar_state(self.weight_decay));
+        state_dict.insert(
+            "nesterov".to_string(),
+            scalar_state(if self.nesterov { 1.0 } else { 0.0 }),
+        );
+        insert_param_states(&mut state_dict, "momentum_buffers", &self.momentum_buffers);
+        state_dict
+    }
+
+    fn load_state_dict(
+        &mut self,
+        state_dict: &BTreeMap<String, Variable>,
+        _metadata: &BTreeMap<String, String>,
+        param_sizes: &[usize],
+    ) -> Result<(), StateDictError> {
+        let lr = get_state(state_dict, "lr", &[1])?.data[0];
+        let momentum = get_state(state_dict, "momentum", &[1])?.data[0];
+        let dampening = get_state(state_dict, "dampening", &[1])?.data[0];
+        let weight_decay = get_state(state_dict, "weight_decay", &[1])?.data[0];
+        let nesterov = get_state(state_dict, "nesterov", &[1])?.data[0] != 0.0;
+        let momentum_buffers = get_param_states(state_dict, "momentum_buffers", param_sizes)?;
+
+        self.lr = lr;
+        self.momentum = momentum;
+        self.dampening = dampening;
+        self.weight_decay = weight_decay;
+        self.nesterov = nesterov;
+        self.momentum_buffers = momentum_buffers;
+        Ok(())
+    }
 }
@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::module::Module;
+use crate::variable::Variable;
+
+pub struct BatchNorm {
+    weight: Rc<RefCell<Variable>>,
+    bias: Rc<RefCell<Variable>>,
+    running_mean: Rc<RefCell<Variable>>,
+    running_var: Rc<RefCell<Variable>>,
+    num_features: usize,
+    // rank of inputs: 2 for (N, C) and 4 for (N, C, H, W)
+    ndim: usize,
+    momentum: f32,
+    eps: f32,
+    training: bool,
+}
+
+impl BatchNorm {
+    pub fn new(num_features: usize, ndim: usize, momentum: f32, eps: f32) -> Self {
+        assert!(ndim == 2 || ndim == 4);
+
+        let weight = Rc::new(RefCell::new(Variable::new(vec![num_features])));
+        let bias = Rc::new(RefCell::new(Variable::new(vec![num_features])));
+        let running_mean = Rc::new(RefCell::new(Variable::new(vec![num_features])));
+        let running_var = Rc::new(RefCell::new(Variable::new(vec![num_features])));
+
+        // initialize scale with ones and shift with zeros
+        weight.borrow_mut().ones();
+        bias.borrow_mut().zeros();
+
+        // buffers are not trained
+        running_mean.borrow_mut().zeros();
+        running_var.borrow_mut().ones();
+        running_mean.borrow_mut().set_need_grad(false);
+        running_var.borrow_mut().set_need_grad(false);
+
+        Self {
+            weight,
+            bias,
+            running_mean,
+            running_var,
+            num_features,
+            ndim,
+            momentum,
+            eps,
+            training: true,
+        }
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        assert_eq!(x.borrow().shape.len(), self.ndim);
+        assert_eq!(x.borrow().shape[1], self.num_features);
+
+        // statistics are computed over every axis except channels
+        let (axes, stats_shape) = if self.ndim == 2 {
+            (vec![0], vec![1, self.num_features])
+        } else {
+            (vec![0, 2, 3], vec![1, self.num_features, 1, 1])
+        };
+
+        let (mean, var) = if self.training {
+            let mean = F::mean(x.clone(), &axes, true);
+            let var = F::var(x.clone(), &axes, true);
+            self.update_running_stats(&mean.borrow().data, &var.borrow().data, &x.borrow());
+            (mean, var)
+        } else {
+            let mean = F::reshape(self.running_mean.clone(), stats_shape.clone());
+            let var = F::reshape(self.running_var.clone(), stats_shape.clone());
+            mean.borrow_mut().set_need_grad(false);
+            var.borrow_mut().set_need_grad(false);
+            (mean, var)
+        };
+
+        let eps = Rc::new(RefCell::new(Variable::new(vec![1])));
+        eps.borrow_mut().data[0] = self.eps;
+        eps.borrow_mut().set_need_grad(false);
+
+        let std = F::sqrt(F::add(var, eps));
+        let normalized = F::div(F::sub(x, mean), std);
+        let weight = F::reshape(self.weight.clone(), stats_shape.clone());
+        let bias = F::reshape(self.bias.clone(), stats_shape);
+        F::add(F::mul(normalized, weight), bias)
+    }
+
+    fn update_running_stats(&self, mean: &[f32], var: &[f32], x: &Variable) {
+        // running variance is unbiased
+        let count = (x.size() / self.num_features) as f32;
+        let correction = if count > 1.0 {
+            count / (count - 1.0)
+        } else {
+            1.0
+        };
+
+        let mut running_mean = self.running_mean.borrow_mut();
+        let mut running_var = self.running_var.borrow_mut();
+        for i in 0..self.num_features {
+            running_mean.data[i] =
+                (1.0 - self.momentum) * running_mean.data[i] + self.momentum * mean[i];
+            running_var.data[i] =
+                (1.0 - self.momentum) * running_var.data[i] + self.momentum * var[i] * correction;
+        }
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![self.weight.clone(), self.bias.clone()]
+    }
+
+    pub fn get_buffers(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![self.running_mean.clone(), self.running_var.clone()]
+    }
+}
+
+impl Module for BatchNorm {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+
+    fn local_buffers(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![
+            ("running_mean".to_string(), self.running_mean.clone()),
+            ("running_var".to_string(), self.running_var.clone()),
+        ]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
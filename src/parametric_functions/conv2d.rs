@@ -0,0 +1,116 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::init::{self, Initializer};
+use crate::module::Module;
+use crate::variable::Variable;
+
+pub struct Conv2d {
+    weight: Rc<RefCell<Variable>>,
+    bias: Rc<RefCell<Variable>>,
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+    groups: usize,
+}
+
+impl Conv2d {
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        dilation: (usize, usize),
+        groups: usize,
+    ) -> Self {
+        // uniform distribution scaled by fan-in
+        Self::with_init(
+            in_channels,
+            out_channels,
+            kernel_size,
+            stride,
+            padding,
+            dilation,
+            groups,
+            init::kaiming_uniform(1.0),
+            init::zeros(),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_init(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        dilation: (usize, usize),
+        groups: usize,
+        weight_init: Box<dyn Initializer>,
+        bias_init: Box<dyn Initializer>,
+    ) -> Self {
+        assert_eq!(in_channels % groups, 0);
+        assert_eq!(out_channels % groups, 0);
+
+        let weight = Rc::new(RefCell::new(Variable::new(vec![
+            out_channels,
+            in_channels / groups,
+            kernel_size.0,
+            kernel_size.1,
+        ])));
+        let bias = Rc::new(RefCell::new(Variable::new(vec![out_channels])));
+
+        let conv = Self {
+            weight,
+            bias,
+            stride,
+            padding,
+            dilation,
+            groups,
+        };
+
+        conv.initialize(weight_init, bias_init);
+        conv
+    }
+
+    pub fn initialize(&self, weight_init: Box<dyn Initializer>, bias_init: Box<dyn Initializer>) {
+        let (fan_in, fan_out) = {
+            let shape = &self.weight.borrow().shape;
+            let receptive_field = shape[2] * shape[3];
+            (shape[1] * receptive_field, shape[0] * receptive_field)
+        };
+        weight_init.initialize(&mut self.weight.borrow_mut(), fan_in, fan_out);
+        bias_init.initialize(&mut self.bias.borrow_mut(), fan_in, fan_out);
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        F::conv2d(
+            x,
+            self.weight.clone(),
+            Some(self.bias.clone()),
+            self.stride,
+            self.padding,
+            self.dilation,
+            self.groups,
+        )
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![self.weight.clone(), self.bias.clone()]
+    }
+}
+
+impl Module for Conv2d {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+}
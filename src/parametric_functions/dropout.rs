@@ -0,0 +1,39 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::module::Module;
+use crate::variable::Variable;
+
+pub struct Dropout {
+    p: f32,
+    training: bool,
+}
+
+impl Dropout {
+    pub fn new(p: f32) -> Self {
+        Self { p, training: true }
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        F::dropout(x, self.p, self.training)
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![]
+    }
+}
+
+impl Module for Dropout {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::module::Module;
+use crate::variable::Variable;
+
+pub struct LayerNorm {
+    weight: Rc<RefCell<Variable>>,
+    bias: Rc<RefCell<Variable>>,
+    normalized_shape: Vec<usize>,
+    eps: f32,
+}
+
+impl LayerNorm {
+    pub fn new(normalized_shape: Vec<usize>, eps: f32) -> Self {
+        let weight = Rc::new(RefCell::new(Variable::new(normalized_shape.clone())));
+        let bias = Rc::new(RefCell::new(Variable::new(normalized_shape.clone())));
+
+        // initialize scale with ones and shift with zeros
+        weight.borrow_mut().ones();
+        bias.borrow_mut().zeros();
+
+        Self {
+            weight,
+            bias,
+            normalized_shape,
+            eps,
+        }
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        F::layer_norm(
+            x,
+            &self.normalized_shape,
+            Some(self.weight.clone()),
+            Some(self.bias.clone()),
+            self.eps,
+        )
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![self.weight.clone(), self.bias.clone()]
+    }
+}
+
+impl Module for LayerNorm {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+}
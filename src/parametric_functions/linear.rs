@@ -2,37 +2,63 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::functions as F;
+use crate::init::{self, Initializer};
+use crate::module::Module;
 use crate::variable::Variable;
 
 pub struct Linear {
     weight: Rc<RefCell<Variable>>,
     bias: Rc<RefCell<Variable>>,
-    out_size: usize,
 }
 
 impl Linear {
     pub fn new(in_size: usize, out_size: usize) -> Self {
-        let weight = Rc::new(RefCell::new(Variable::rand(vec![in_size, out_size])));
+        // uniform distribution scaled by fan-in
+        Self::with_init(in_size, out_size, init::kaiming_uniform(1.0), init::zeros())
+    }
+
+    pub fn with_init(
+        in_size: usize,
+        out_size: usize,
+        weight_init: Box<dyn Initializer>,
+        bias_init: Box<dyn Initializer>,
+    ) -> Self {
+        let weight = Rc::new(RefCell::new(Variable::new(vec![in_size, out_size])));
         let bias = Rc::new(RefCell::new(Variable::new(vec![1, out_size])));
 
-        // initialize bias with zeros
-        bias.borrow_mut().zeros();
+        let linear = Self { weight, bias };
+        linear.initialize(weight_init, bias_init);
+        linear
+    }
 
-        Self {
-            weight,
-            bias,
-            out_size,
-        }
+    pub fn initialize(&self, weight_init: Box<dyn Initializer>, bias_init: Box<dyn Initializer>) {
+        let (fan_in, fan_out) = {
+            let weight = self.weight.borrow();
+            (weight.shape[0], weight.shape[1])
+        };
+        weight_init.initialize(&mut self.weight.borrow_mut(), fan_in, fan_out);
+        bias_init.initialize(&mut self.bias.borrow_mut(), fan_in, fan_out);
     }
 
     pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
-        let batch_size = x.borrow().shape[0];
         let h = F::matmul(x, self.weight.clone());
-        let broadcasted_bias = F::broadcast(self.bias.clone(), vec![batch_size, self.out_size]);
-        F::add(h, broadcasted_bias)
+        F::add(h, self.bias.clone())
     }
 
     pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
         vec![self.weight.clone(), self.bias.clone()]
     }
 }
+
+impl Module for Linear {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+}
@@ -1,17 +1,150 @@
+use crate::init::Initializer;
+
+mod batch_norm;
+mod conv2d;
+mod dropout;
+mod layer_norm;
 mod linear;
+mod rms_norm;
+mod sequential;
+
+pub use batch_norm::BatchNorm;
+pub use conv2d::Conv2d;
+pub use dropout::Dropout;
+pub use layer_norm::LayerNorm;
+pub use linear::Linear;
+pub use rms_norm::RmsNorm;
+pub use sequential::Sequential;
+
+pub fn batch_norm1d(num_features: usize) -> Box<batch_norm::BatchNorm> {
+    Box::new(batch_norm::BatchNorm::new(num_features, 2, 0.1, 1e-5))
+}
+
+pub fn batch_norm2d(num_features: usize) -> Box<batch_norm::BatchNorm> {
+    Box::new(batch_norm::BatchNorm::new(num_features, 4, 0.1, 1e-5))
+}
+
+pub fn conv2d(
+    in_channels: usize,
+    out_channels: usize,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+    groups: usize,
+) -> Box<conv2d::Conv2d> {
+    Box::new(conv2d::Conv2d::new(
+        in_channels,
+        out_channels,
+        kernel_size,
+        stride,
+        padding,
+        dilation,
+        groups,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn conv2d_with(
+    in_channels: usize,
+    out_channels: usize,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+    groups: usize,
+    weight_init: Box<dyn Initializer>,
+    bias_init: Box<dyn Initializer>,
+) -> Box<conv2d::Conv2d> {
+    Box::new(conv2d::Conv2d::with_init(
+        in_channels,
+        out_channels,
+        kernel_size,
+        stride,
+        padding,
+        dilation,
+        groups,
+        weight_init,
+        bias_init,
+    ))
+}
+
+pub fn dropout(p: f32) -> Box<dropout::Dropout> {
+    Box::new(dropout::Dropout::new(p))
+}
+
+pub fn layer_norm(normalized_shape: Vec<usize>) -> Box<layer_norm::LayerNorm> {
+    Box::new(layer_norm::LayerNorm::new(normalized_shape, 1e-5))
+}
 
 pub fn linear(in_size: usize, out_size: usize) -> Box<linear::Linear> {
     Box::new(linear::Linear::new(in_size, out_size))
 }
 
+pub fn linear_with(
+    in_size: usize,
+    out_size: usize,
+    weight_init: Box<dyn Initializer>,
+    bias_init: Box<dyn Initializer>,
+) -> Box<linear::Linear> {
+    Box::new(linear::Linear::with_init(
+        in_size,
+        out_size,
+        weight_init,
+        bias_init,
+    ))
+}
+
+pub fn rms_norm(normalized_shape: Vec<usize>) -> Box<rms_norm::RmsNorm> {
+    Box::new(rms_norm::RmsNorm::new(normalized_shape, 1e-6))
+}
+
+pub fn sequential() -> Box<sequential::Sequential> {
+    Box::new(sequential::Sequential::new())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::graph::backward;
+    use crate::module::Module;
     use crate::variable::Variable;
     use std::cell::RefCell;
     use std::rc::Rc;
 
+    #[test]
+    fn dropout_train_eval() {
+        let mut drop = dropout(0.5);
+        let x = Rc::new(RefCell::new(Variable::new(vec![4, 100])));
+        x.borrow_mut().ones();
+
+        let output = drop.call(x.clone());
+        assert!(output.borrow().data.contains(&0.0));
+
+        // identity in evaluation mode
+        drop.eval();
+        assert!(!drop.is_training());
+        let output = drop.call(x.clone());
+        assert!(Rc::ptr_eq(&output, &x));
+    }
+
+    #[test]
+    fn layer_norm_forward_backward() {
+        let fc = linear(10, 16);
+        let ln = layer_norm(vec![16]);
+
+        let x = Rc::new(RefCell::new(Variable::rand(vec![8, 10])));
+        let output = ln.call(fc.call(x));
+        backward(output.clone());
+
+        assert_eq!(output.borrow().shape, vec![8, 16]);
+        let params = ln.get_params();
+        assert_eq!(params[1].borrow().grad, vec![8.0; 16]);
+        for param in fc.get_params() {
+            assert!(param.borrow().grad.iter().all(|g| g.is_finite()));
+        }
+    }
+
     #[test]
     fn linear_forward_backward() {
         let fc1 = linear(100, 200);
@@ -26,4 +159,213 @@ mod tests {
 
         backward(output);
     }
+
+    #[test]
+    fn batch_norm1d_train_eval() {
+        let mut bn = batch_norm1d(3);
+        assert_eq!(bn.get_params().len(), 2);
+        assert_eq!(bn.get_buffers().len(), 2);
+
+        let x = Rc::new(RefCell::new(Variable::new(vec![4, 3])));
+        x.borrow_mut()
+            .set_data(&[1.0, 0.0, 5.0, 2.0, 0.0, 5.0, 3.0, 0.0, 5.0, 4.0, 0.0, 5.0]);
+        let output = bn.call(x.clone());
+        backward(output.clone());
+
+        // normalized with batch statistics
+        let y = &output.borrow().data;
+        let expected = [-1.3416, -0.4472, 0.4472, 1.3416];
+        for i in 0..4 {
+            assert!((y[i * 3] - expected[i]).abs() < 0.001);
+            assert_eq!(y[i * 3 + 1], 0.0);
+            assert_eq!(y[i * 3 + 2], 0.0);
+        }
+
+        // running statistics are updated with momentum and unbiased variance
+        let buffers = bn.get_buffers();
+        let running_mean = buffers[0].borrow().data.clone();
+        let running_var = buffers[1].borrow().data.clone();
+        assert!((running_mean[0] - 0.25).abs() < 1e-6);
+        assert!((running_mean[2] - 0.5).abs() < 1e-6);
+        assert!((running_var[0] - (0.9 + 0.1 * 5.0 / 3.0)).abs() < 1e-6);
+        assert!((running_var[1] - 0.9).abs() < 1e-6);
+
+        // gradients reach scale and shift but not buffers
+        let params = bn.get_params();
+        assert!((params[0].borrow().grad[0]).abs() < 1e-5);
+        assert_eq!(params[1].borrow().grad, vec![4.0; 3]);
+        assert_eq!(buffers[0].borrow().grad, vec![0.0; 3]);
+
+        // inference uses running statistics
+        bn.eval();
+        assert!(!bn.is_training());
+        let output = bn.call(x);
+        let expected = (1.0 - 0.25) / (0.9f32 + 0.5 / 3.0 + 1e-5).sqrt();
+        assert!((output.borrow().data[0] - expected).abs() < 1e-5);
+        assert_eq!(buffers[0].borrow().data, running_mean);
+    }
+
+    #[test]
+    fn batch_norm2d_forward_backward() {
+        let bn = batch_norm2d(4);
+
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 4, 3, 3])));
+        let output = bn.call(x);
+        backward(output.clone());
+        assert_eq!(output.borrow().shape, vec![2, 4, 3, 3]);
+
+        // every channel has zero mean and unit variance
+        let y = &output.borrow().data;
+        for c in 0..4 {
+            let mut values = vec![];
+            for n in 0..2 {
+                let offset = (n * 4 + c) * 9;
+                values.extend_from_slice(&y[offset..offset + 9]);
+            }
+            let mean: f32 = values.iter().sum::<f32>() / 18.0;
+            let var: f32 = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / 18.0;
+            assert!(mean.abs() < 1e-4);
+            assert!((var - 1.0).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn linear_initializers() {
+        use crate::init;
+
+        let fc = linear_with(4, 3, init::constant(0.5), init::constant(-1.0));
+        let params = fc.get_params();
+        assert_eq!(params[0].borrow().data, vec![0.5; 12]);
+        assert_eq!(params[1].borrow().data, vec![-1.0; 3]);
+
+        // default weights are scaled by fan-in
+        let fc = linear(10000, 2);
+        let bound = (3.0f32 / 10000.0).sqrt();
+        let weight = &fc.get_params()[0];
+        assert!(weight.borrow().data.iter().all(|v| v.abs() <= bound));
+
+        let conv = conv2d_with(
+            2,
+            4,
+            (3, 3),
+            (1, 1),
+            (0, 0),
+            (1, 1),
+            1,
+            init::custom(|variable, fan_in, fan_out| {
+                variable.data.fill((fan_in * 100 + fan_out) as f32)
+            }),
+            init::constant(0.25),
+        );
+        assert_eq!(conv.get_params()[0].borrow().data[0], 1836.0);
+        assert_eq!(conv.get_params()[1].borrow().data, vec![0.25; 4]);
+    }
+
+    #[test]
+    fn rms_norm_forward_backward() {
+        let norm = rms_norm(vec![3, 4]);
+
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 3, 4])));
+        let output = norm.call(x);
+        backward(output.clone());
+
+        // outputs have unit root mean square
+        let y = &output.borrow().data;
+        for i in 0..2 {
+            let mean_square: f32 =
+                y[i * 12..(i + 1) * 12].iter().map(|v| v * v).sum::<f32>() / 12.0;
+            assert!((mean_square - 1.0).abs() < 1e-3);
+        }
+        assert_eq!(norm.get_params()[0].borrow().shape, vec![3, 4]);
+    }
+
+    #[test]
+    fn sequential_forward_backward() {
+        use crate::functions as F;
+
+        let mut model = sequential()
+            .then(linear(10, 16))
+            .then_fn(F::relu)
+            .then(batch_norm1d(16))
+            .then(dropout(0.5))
+            .then_fn(|x| F::mul(x.clone(), x))
+            .then(linear(16, 3));
+        assert_eq!(model.len(), 6);
+
+        let names: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["0.weight", "0.bias", "2.weight", "2.bias", "5.weight", "5.bias"]
+        );
+        assert_eq!(model.named_buffers().len(), 2);
+        assert_eq!(model.get_params().len(), 6);
+
+        let x = Rc::new(RefCell::new(Variable::rand(vec![4, 10])));
+        let output = model.forward(x.clone());
+        assert_eq!(output.borrow().shape, vec![4, 3]);
+        backward(output);
+        assert!(model.parameters()[0]
+            .borrow()
+            .grad
+            .iter()
+            .any(|g| *g != 0.0));
+
+        // evaluation mode is deterministic with dropout and batch norm
+        model.eval();
+        let y1 = model.forward(x.clone());
+        let y2 = model.forward(x);
+        assert_eq!(y1.borrow().data, y2.borrow().data);
+    }
+
+    #[test]
+    fn sequential_names_follow_execution_order() {
+        let mut model = sequential();
+        for i in 0..12 {
+            model.push(linear(i + 1, i + 2));
+        }
+
+        // names are plain indices, and parameters follow the execution order
+        // rather than the lexical order of names
+        let names: Vec<String> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names[..4], ["0.weight", "0.bias", "1.weight", "1.bias"]);
+        assert_eq!(
+            names[18..22],
+            ["9.weight", "9.bias", "10.weight", "10.bias"]
+        );
+
+        // weights have increasing sizes in execution order
+        let weights: Vec<Vec<usize>> = model
+            .parameters()
+            .iter()
+            .step_by(2)
+            .map(|w| w.borrow().shape.clone())
+            .collect();
+        let expected: Vec<Vec<usize>> = (0..12).map(|i| vec![i + 1, i + 2]).collect();
+        assert_eq!(weights, expected);
+    }
+
+    #[test]
+    fn conv2d_forward_backward() {
+        let conv1 = conv2d(1, 6, (5, 5), (1, 1), (2, 2), (1, 1), 1);
+        let conv2 = conv2d(6, 16, (5, 5), (2, 2), (0, 0), (1, 1), 2);
+
+        let x = Rc::new(RefCell::new(Variable::rand(vec![2, 1, 28, 28])));
+        let h = conv1.call(x);
+        let output = conv2.call(h);
+
+        assert_eq!(output.borrow().shape, vec![2, 16, 12, 12]);
+
+        backward(output);
+        for param in conv1.get_params() {
+            assert!(param.borrow().grad.iter().any(|g| *g != 0.0));
+        }
+    }
 }
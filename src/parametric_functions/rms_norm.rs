@@ -0,0 +1,50 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::module::Module;
+use crate::variable::Variable;
+
+pub struct RmsNorm {
+    weight: Rc<RefCell<Variable>>,
+    normalized_shape: Vec<usize>,
+    eps: f32,
+}
+
+impl RmsNorm {
+    pub fn new(normalized_shape: Vec<usize>, eps: f32) -> Self {
+        let weight = Rc::new(RefCell::new(Variable::new(normalized_shape.clone())));
+
+        // initialize scale with ones
+        weight.borrow_mut().ones();
+
+        Self {
+            weight,
+            normalized_shape,
+            eps,
+        }
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        F::rms_norm(
+            x,
+            &self.normalized_shape,
+            Some(self.weight.clone()),
+            self.eps,
+        )
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        vec![self.weight.clone()]
+    }
+}
+
+impl Module for RmsNorm {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn local_parameters(&self) -> Vec<(String, Rc<RefCell<Variable>>)> {
+        vec![("weight".to_string(), self.weight.clone())]
+    }
+}
@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::module::Module;
+use crate::variable::Variable;
+
+// wraps a function without parameters such as an activation
+struct Lambda<T: Fn(Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>>> {
+    function: T,
+}
+
+impl<T: Fn(Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>>> Module for Lambda<T> {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        (self.function)(x)
+    }
+}
+
+// runs modules in order and names them by their indices
+#[derive(Default)]
+pub struct Sequential {
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl Sequential {
+    pub fn new() -> Self {
+        Self { modules: vec![] }
+    }
+
+    pub fn then(mut self, module: Box<dyn Module>) -> Self {
+        self.push(module);
+        self
+    }
+
+    pub fn then_fn<T>(mut self, function: T) -> Self
+    where
+        T: Fn(Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> + 'static,
+    {
+        self.push_fn(function);
+        self
+    }
+
+    pub fn push(&mut self, module: Box<dyn Module>) {
+        self.modules.push(module);
+    }
+
+    pub fn push_fn<T>(&mut self, function: T)
+    where
+        T: Fn(Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> + 'static,
+    {
+        self.modules.push(Box::new(Lambda { function }));
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn call(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        let mut h = x;
+        for module in self.modules.iter() {
+            h = module.forward(h);
+        }
+        h
+    }
+
+    pub fn get_params(&self) -> Vec<Rc<RefCell<Variable>>> {
+        self.parameters()
+    }
+}
+
+impl Module for Sequential {
+    fn forward(&self, x: Rc<RefCell<Variable>>) -> Rc<RefCell<Variable>> {
+        self.call(x)
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(i, module)| (i.to_string(), module.as_ref()))
+            .collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<(String, &mut dyn Module)> {
+        let mut children: Vec<(String, &mut dyn Module)> = vec![];
+        for (i, module) in self.modules.iter_mut().enumerate() {
+            children.push((i.to_string(), module.as_mut()));
+        }
+        children
+    }
+}
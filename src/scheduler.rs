@@ -0,0 +1,52 @@
+use crate::optimizer::Optimizer;
+
+pub trait Schedule {
+    // learning rate for the given number of past steps
+    fn lr(&self, step: usize, base_lr: f32) -> f32;
+}
+
+// `step` is called before each update or epoch, and the learning rate of
+// each parameter group at the first call is used as its base learning rate
+pub struct Scheduler {
+    schedule: Box<dyn Schedule>,
+    base_lrs: Vec<Option<f32>>,
+    steps: usize,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Box<dyn Schedule>) -> Self {
+        Self {
+            schedule,
+            base_lrs: vec![],
+            steps: 0,
+        }
+    }
+
+    fn set_group_lr(&mut self, optimizer: &mut Optimizer, group: usize) {
+        if self.base_lrs.len() <= group {
+            self.base_lrs.resize(group + 1, None);
+        }
+        let base_lr = *self.base_lrs[group].get_or_insert(optimizer.group_lr(group));
+        optimizer.set_group_lr(group, self.schedule.lr(self.steps, base_lr));
+    }
+
+    // drives every parameter group
+    pub fn step(&mut self, optimizer: &mut Optimizer) {
+        for group in 0..optimizer.num_groups() {
+            self.set_group_lr(optimizer, group);
+        }
+        self.steps += 1;
+    }
+
+    // drives only the given group so that groups can follow different
+    // schedulers
+    pub fn step_group(&mut self, optimizer: &mut Optimizer, group: usize) {
+        self.set_group_lr(optimizer, group);
+        self.steps += 1;
+    }
+
+    // schedule without the step count and base learning rates
+    pub(crate) fn into_schedule(self) -> Box<dyn Schedule> {
+        self.schedule
+    }
+}
@@ -0,0 +1,54 @@
+use std::f32::consts::PI;
+
+use crate::scheduler::Schedule;
+
+// SGDR: https://arxiv.org/abs/1608.03983
+pub struct CosineAnnealingWarmRestarts {
+    // steps until the first restart
+    pub t_0: usize,
+    // factor to grow cycles after each restart
+    pub t_mult: usize,
+    pub eta_min: f32,
+}
+
+impl CosineAnnealingWarmRestarts {
+    // start of the cycle n, namely t_0 * (t_mult^n - 1) / (t_mult - 1) for
+    // t_mult > 1, or None beyond usize
+    fn cycle_start(&self, n: u32) -> Option<usize> {
+        let sum = (self.t_mult.checked_pow(n)? - 1) / (self.t_mult - 1);
+        self.t_0.checked_mul(sum)
+    }
+
+    // steps since the last restart and the length of the current cycle,
+    // in closed form since the learning rate is computed for every step
+    pub(super) fn cycle(&self, step: usize) -> (usize, usize) {
+        if self.t_mult == 1 {
+            return (step % self.t_0, self.t_0);
+        }
+
+        // the logarithm is corrected with exact integers around cycle starts
+        let t_mult = self.t_mult as f64;
+        let cycles = step as f64 * (t_mult - 1.0) / self.t_0 as f64 + 1.0;
+        let mut n = (cycles.ln() / t_mult.ln()).floor() as u32;
+        while n > 0 && self.cycle_start(n).is_none_or(|start| start > step) {
+            n -= 1;
+        }
+        while self.cycle_start(n + 1).is_some_and(|start| start <= step) {
+            n += 1;
+        }
+        let t_i = self
+            .t_mult
+            .checked_pow(n)
+            .and_then(|size| size.checked_mul(self.t_0))
+            .unwrap_or(usize::MAX);
+        (step - self.cycle_start(n).unwrap(), t_i)
+    }
+}
+
+impl Schedule for CosineAnnealingWarmRestarts {
+    fn lr(&self, step: usize, base_lr: f32) -> f32 {
+        let (t_cur, t_i) = self.cycle(step);
+        let cos = (PI * t_cur as f32 / t_i as f32).cos();
+        self.eta_min + (base_lr - self.eta_min) * (1.0 + cos) / 2.0
+    }
+}
@@ -0,0 +1,11 @@
+use crate::scheduler::Schedule;
+
+pub struct ExponentialLr {
+    pub gamma: f32,
+}
+
+impl Schedule for ExponentialLr {
+    fn lr(&self, step: usize, base_lr: f32) -> f32 {
+        base_lr * self.gamma.powi(step as i32)
+    }
+}
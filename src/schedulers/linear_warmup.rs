@@ -0,0 +1,22 @@
+use crate::scheduler::Schedule;
+
+// scales the learning rate linearly from start_factor to 1, then follows
+// another schedule counting steps from the end of warmup
+pub struct LinearWarmup {
+    pub warmup_steps: usize,
+    pub start_factor: f32,
+    pub after: Option<Box<dyn Schedule>>,
+}
+
+impl Schedule for LinearWarmup {
+    fn lr(&self, step: usize, base_lr: f32) -> f32 {
+        if step < self.warmup_steps {
+            let progress = step as f32 / self.warmup_steps as f32;
+            return base_lr * (self.start_factor + (1.0 - self.start_factor) * progress);
+        }
+        match &self.after {
+            Some(after) => after.lr(step - self.warmup_steps, base_lr),
+            None => base_lr,
+        }
+    }
+}
@@ -0,0 +1,290 @@
+mod cosine_annealing_warm_restarts;
+mod exponential_lr;
+mod linear_warmup;
+mod multi_step_lr;
+mod one_cycle;
+mod reduce_on_plateau;
+mod step_lr;
+
+pub use reduce_on_plateau::{PlateauMode, ReduceOnPlateau};
+
+use crate::scheduler::Scheduler;
+
+// decays the learning rate by gamma every step_size steps
+pub fn step_lr(step_size: usize, gamma: f32) -> Box<Scheduler> {
+    assert!(step_size > 0);
+    let schedule = Box::new(step_lr::StepLr { step_size, gamma });
+    Box::new(Scheduler::new(schedule))
+}
+
+// decays the learning rate by gamma at each milestone
+pub fn multi_step_lr(milestones: Vec<usize>, gamma: f32) -> Box<Scheduler> {
+    let schedule = Box::new(multi_step_lr::MultiStepLr { milestones, gamma });
+    Box::new(Scheduler::new(schedule))
+}
+
+pub fn exponential_lr(gamma: f32) -> Box<Scheduler> {
+    let schedule = Box::new(exponential_lr::ExponentialLr { gamma });
+    Box::new(Scheduler::new(schedule))
+}
+
+pub fn cosine_annealing_warm_restarts(t_0: usize, t_mult: usize, eta_min: f32) -> Box<Scheduler> {
+    assert!(t_0 > 0 && t_mult > 0);
+    let schedule = Box::new(
+        cosine_annealing_warm_restarts::CosineAnnealingWarmRestarts {
+            t_0,
+            t_mult,
+            eta_min,
+        },
+    );
+    Box::new(Scheduler::new(schedule))
+}
+
+pub fn linear_warmup(warmup_steps: usize, start_factor: f32) -> Box<Scheduler> {
+    let schedule = Box::new(linear_warmup::LinearWarmup {
+        warmup_steps,
+        start_factor,
+        after: None,
+    });
+    Box::new(Scheduler::new(schedule))
+}
+
+// warms up and then follows the schedule of the given scheduler, whose own
+// steps are counted from the end of warmup
+#[allow(clippy::boxed_local)]
+pub fn linear_warmup_then(
+    warmup_steps: usize,
+    start_factor: f32,
+    after: Box<Scheduler>,
+) -> Box<Scheduler> {
+    let schedule = Box::new(linear_warmup::LinearWarmup {
+        warmup_steps,
+        start_factor,
+        after: Some(after.into_schedule()),
+    });
+    Box::new(Scheduler::new(schedule))
+}
+
+pub fn one_cycle(max_lr: f32, total_steps: usize) -> Box<Scheduler> {
+    one_cycle_with(max_lr, total_steps, 0.3, 25.0, 1e4)
+}
+
+pub fn one_cycle_with(
+    max_lr: f32,
+    total_steps: usize,
+    pct_start: f32,
+    div_factor: f32,
+    final_div_factor: f32,
+) -> Box<Scheduler> {
+    assert!(pct_start > 0.0 && pct_start < 1.0);
+    let schedule = Box::new(one_cycle::OneCycle {
+        max_lr,
+        total_steps,
+        pct_start,
+        div_factor,
+        final_div_factor,
+    });
+    Box::new(Scheduler::new(schedule))
+}
+
+pub fn reduce_on_plateau(mode: PlateauMode, factor: f32, patience: usize) -> Box<ReduceOnPlateau> {
+    Box::new(ReduceOnPlateau::new(mode, factor, patience))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimizer::Optimizer;
+    use crate::optimizers as S;
+    use crate::variable::Variable;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // learning rates used for each update
+    fn lrs(scheduler: &mut Scheduler, optim: &mut Optimizer, steps: usize) -> Vec<f32> {
+        let mut lrs = vec![];
+        for _ in 0..steps {
+            scheduler.step(optim);
+            lrs.push(optim.lr());
+        }
+        lrs
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn step_schedulers() {
+        let mut optim = S::sgd(1.0);
+        assert_close(
+            &lrs(&mut step_lr(2, 0.5), &mut optim, 5),
+            &[1.0, 1.0, 0.5, 0.5, 0.25],
+        );
+
+        let mut optim = S::sgd(1.0);
+        assert_close(
+            &lrs(&mut multi_step_lr(vec![1, 3], 0.1), &mut optim, 5),
+            &[1.0, 0.1, 0.1, 0.01, 0.01],
+        );
+
+        let mut optim = S::adam(1.0, (0.9, 0.999), 1e-8);
+        assert_close(
+            &lrs(&mut exponential_lr(0.5), &mut optim, 3),
+            &[1.0, 0.5, 0.25],
+        );
+    }
+
+    #[test]
+    fn cosine_annealing_warm_restarts_scheduler() {
+        // cycles of 2 and 4 steps
+        let mut optim = S::sgd(1.0);
+        assert_close(
+            &lrs(
+                &mut cosine_annealing_warm_restarts(2, 2, 0.0),
+                &mut optim,
+                7,
+            ),
+            &[1.0, 0.5, 1.0, 0.8535534, 0.5, 0.1464466, 1.0],
+        );
+
+        // closed form agrees with restarting cycle by cycle
+        for (t_0, t_mult) in [(1, 1), (3, 1), (1, 2), (2, 2), (5, 3), (1, 10)] {
+            let schedule = cosine_annealing_warm_restarts::CosineAnnealingWarmRestarts {
+                t_0,
+                t_mult,
+                eta_min: 0.0,
+            };
+            let (mut t_cur, mut t_i) = (0, t_0);
+            for step in 0..2000 {
+                assert_eq!(schedule.cycle(step), (t_cur, t_i));
+                t_cur += 1;
+                if t_cur == t_i {
+                    t_cur = 0;
+                    t_i *= t_mult;
+                }
+            }
+        }
+
+        // without iterating over past cycles
+        let schedule = cosine_annealing_warm_restarts::CosineAnnealingWarmRestarts {
+            t_0: 3,
+            t_mult: 2,
+            eta_min: 0.0,
+        };
+        assert_eq!(
+            schedule.cycle(usize::MAX),
+            (usize::MAX - 3 * (1 << 62) + 3, 3 << 62)
+        );
+        let schedule = cosine_annealing_warm_restarts::CosineAnnealingWarmRestarts {
+            t_0: 3,
+            t_mult: 1,
+            eta_min: 0.0,
+        };
+        assert_eq!(schedule.cycle(1 << 40), ((1 << 40) % 3, 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn step_lr_zero_step_size() {
+        step_lr(0, 0.5);
+    }
+
+    #[test]
+    fn linear_warmup_scheduler() {
+        let mut optim = S::sgd(1.0);
+        assert_close(
+            &lrs(&mut linear_warmup(4, 0.2), &mut optim, 6),
+            &[0.2, 0.4, 0.6, 0.8, 1.0, 1.0],
+        );
+
+        let mut optim = S::sgd(1.0);
+        let mut scheduler = linear_warmup_then(2, 0.5, step_lr(1, 0.5));
+        assert_close(&lrs(&mut scheduler, &mut optim, 4), &[0.5, 0.75, 1.0, 0.5]);
+
+        // steps already taken by the following scheduler are not counted
+        let mut optim = S::sgd(1.0);
+        let mut after = step_lr(1, 0.5);
+        after.step(&mut S::sgd(1.0));
+        let mut scheduler = linear_warmup_then(1, 0.5, after);
+        assert_close(&lrs(&mut scheduler, &mut optim, 3), &[0.5, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn one_cycle_scheduler() {
+        // increases for 4 steps and decreases for 5 steps
+        let mut optim = S::sgd(1.0);
+        let lrs = lrs(
+            &mut one_cycle_with(1.0, 10, 0.5, 10.0, 10.0),
+            &mut optim,
+            10,
+        );
+        assert_close(&[lrs[0], lrs[2], lrs[4], lrs[9]], &[0.1, 0.55, 1.0, 0.01]);
+        assert!(lrs[..5].windows(2).all(|w| w[0] < w[1]));
+        assert!(lrs[4..].windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn one_cycle_single_warmup_step() {
+        // starts at the peak and decreases for 9 steps
+        let mut optim = S::sgd(1.0);
+        let lrs = lrs(
+            &mut one_cycle_with(1.0, 10, 0.1, 10.0, 10.0),
+            &mut optim,
+            10,
+        );
+        assert_close(&[lrs[0], lrs[9]], &[1.0, 0.01]);
+        assert!(lrs.windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn reduce_on_plateau_scheduler() {
+        let mut optim = S::sgd(1.0);
+        let mut scheduler = reduce_on_plateau(PlateauMode::Min, 0.5, 1);
+        let mut lrs = vec![];
+        for loss in [1.0, 0.9, 0.9, 0.9, 0.8, 0.9, 0.9, 0.9] {
+            scheduler.step(&mut optim, loss);
+            lrs.push(optim.lr());
+        }
+        assert_close(&lrs, &[1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.25, 0.25]);
+
+        // accuracy should increase
+        let mut optim = S::sgd(1.0);
+        let mut scheduler = reduce_on_plateau(PlateauMode::Max, 0.1, 0);
+        scheduler.step(&mut optim, 0.5);
+        scheduler.step(&mut optim, 0.6);
+        assert_eq!(optim.lr(), 1.0);
+        scheduler.step(&mut optim, 0.6);
+        assert_close(&[optim.lr()], &[0.1]);
+    }
+
+    #[test]
+    fn param_group_schedulers() {
+        let x = Rc::new(RefCell::new(Variable::new(vec![1])));
+        let y = Rc::new(RefCell::new(Variable::new(vec![1])));
+        let mut optim = S::sgd(1.0);
+        optim.set_params(vec![x]);
+        optim
+            .add_param_group(vec![y], &[("lr", 0.1.into())])
+            .unwrap();
+
+        // each group decays from its own base learning rate
+        let mut scheduler = step_lr(1, 0.5);
+        scheduler.step(&mut optim);
+        scheduler.step(&mut optim);
+        assert_close(&[optim.group_lr(0), optim.group_lr(1)], &[0.5, 0.05]);
+
+        // a scheduler drives only the given group
+        let mut warmup = linear_warmup(2, 0.0);
+        warmup.step_group(&mut optim, 1);
+        assert_close(&[optim.group_lr(0), optim.group_lr(1)], &[0.5, 0.0]);
+
+        let mut plateau = reduce_on_plateau(PlateauMode::Min, 0.1, 0);
+        plateau.step(&mut optim, 1.0);
+        plateau.step(&mut optim, 1.0);
+        assert_close(&[optim.group_lr(0), optim.group_lr(1)], &[0.05, 0.0]);
+    }
+}
@@ -0,0 +1,13 @@
+use crate::scheduler::Schedule;
+
+pub struct MultiStepLr {
+    pub milestones: Vec<usize>,
+    pub gamma: f32,
+}
+
+impl Schedule for MultiStepLr {
+    fn lr(&self, step: usize, base_lr: f32) -> f32 {
+        let passed = self.milestones.iter().filter(|m| **m <= step).count();
+        base_lr * self.gamma.powi(passed as i32)
+    }
+}
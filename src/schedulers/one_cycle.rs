@@ -0,0 +1,39 @@
+use std::f32::consts::PI;
+
+use crate::scheduler::Schedule;
+
+// https://arxiv.org/abs/1708.07120
+// anneals from max_lr / div_factor up to max_lr and then down to
+// max_lr / div_factor / final_div_factor with cosine curves
+pub struct OneCycle {
+    pub max_lr: f32,
+    pub total_steps: usize,
+    // fraction of steps spent increasing the learning rate
+    pub pct_start: f32,
+    pub div_factor: f32,
+    pub final_div_factor: f32,
+}
+
+fn cosine_anneal(start: f32, end: f32, pct: f32) -> f32 {
+    end + (start - end) / 2.0 * ((PI * pct).cos() + 1.0)
+}
+
+impl Schedule for OneCycle {
+    fn lr(&self, step: usize, _base_lr: f32) -> f32 {
+        let initial_lr = self.max_lr / self.div_factor;
+        let min_lr = initial_lr / self.final_div_factor;
+        let peak_step = self.pct_start * self.total_steps as f32 - 1.0;
+        let last_step = self.total_steps as f32 - 1.0;
+
+        // warmup of a single step starts at max_lr
+        let step = step as f32;
+        if step < peak_step {
+            cosine_anneal(initial_lr, self.max_lr, step / peak_step)
+        } else if step == peak_step {
+            self.max_lr
+        } else {
+            let pct = ((step - peak_step) / (last_step - peak_step)).min(1.0);
+            cosine_anneal(self.max_lr, min_lr, pct)
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::optimizer::Optimizer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlateauMode {
+    // the monitored metric should decrease (e.g. loss)
+    Min,
+    // the monitored metric should increase (e.g. accuracy)
+    Max,
+}
+
+// reduces the learning rate by factor when the metric has not improved for
+// more than patience steps
+pub struct ReduceOnPlateau {
+    pub mode: PlateauMode,
+    pub factor: f32,
+    pub patience: usize,
+    // relative improvement required over the best metric
+    pub threshold: f32,
+    // steps to wait after a reduction before counting bad steps again
+    pub cooldown: usize,
+    pub min_lr: f32,
+    best: f32,
+    num_bad_steps: usize,
+    cooldown_counter: usize,
+}
+
+impl ReduceOnPlateau {
+    pub fn new(mode: PlateauMode, factor: f32, patience: usize) -> Self {
+        assert!(factor < 1.0);
+        let best = match mode {
+            PlateauMode::Min => f32::INFINITY,
+            PlateauMode::Max => f32::NEG_INFINITY,
+        };
+        Self {
+            mode,
+            factor,
+            patience,
+            threshold: 1e-4,
+            cooldown: 0,
+            min_lr: 0.0,
+            best,
+            num_bad_steps: 0,
+            cooldown_counter: 0,
+        }
+    }
+
+    fn is_better(&self, metric: f32) -> bool {
+        match self.mode {
+            PlateauMode::Min => metric < self.best * (1.0 - self.threshold),
+            PlateauMode::Max => metric > self.best * (1.0 + self.threshold),
+        }
+    }
+
+    pub fn step(&mut self, optimizer: &mut Optimizer, metric: f32) {
+        if self.is_better(metric) {
+            self.best = metric;
+            self.num_bad_steps = 0;
+        } else {
+            self.num_bad_steps += 1;
+        }
+
+        if self.cooldown_counter > 0 {
+            self.cooldown_counter -= 1;
+            self.num_bad_steps = 0;
+        }
+
+        if self.num_bad_steps > self.patience {
+            for group in 0..optimizer.num_groups() {
+                let lr = (optimizer.group_lr(group) * self.factor).max(self.min_lr);
+                optimizer.set_group_lr(group, lr);
+            }
+            self.cooldown_counter = self.cooldown;
+            self.num_bad_steps = 0;
+        }
+    }
+}
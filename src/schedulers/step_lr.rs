@@ -0,0 +1,12 @@
+use crate::scheduler::Schedule;
+
+pub struct StepLr {
+    pub step_size: usize,
+    pub gamma: f32,
+}
+
+impl Schedule for StepLr {
+    fn lr(&self, step: usize, base_lr: f32) -> f32 {
+        base_lr * self.gamma.powi((step / self.step_size) as i32)
+    }
+}
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::variable::Variable;
+
+pub mod native;
+pub mod npy;
+pub mod npz;
+pub mod safetensors;
+
+#[derive(Debug, PartialEq)]
+pub enum StateDictError {
+    MissingKeys(Vec<String>),
+    UnexpectedKeys(Vec<String>),
+    ShapeMismatch {
+        key: String,
+        expected: Vec<usize>,
+        actual: Vec<usize>,
+    },
+    TypeMismatch {
+        expected: String,
+        actual: String,
+    },
+    InvalidFormat(String),
+}
+
+impl fmt::Display for StateDictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingKeys(keys) => write!(f, "missing keys in state dict: {:?}", keys),
+            Self::UnexpectedKeys(keys) => write!(f, "unexpected keys in state dict: {:?}", keys),
+            Self::ShapeMismatch {
+                key,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "shape mismatch for {}: expected {:?} but got {:?}",
+                key, expected, actual
+            ),
+            Self::TypeMismatch { expected, actual } => {
+                write!(f, "type mismatch: expected {} but got {}", expected, actual)
+            }
+            Self::InvalidFormat(message) => write!(f, "invalid format: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StateDictError {}
+
+// copies loaded values into variables after validating every entry so that
+// nothing is modified on errors
+pub fn copy_state_dict(
+    variables: &BTreeMap<String, Rc<RefCell<Variable>>>,
+    state_dict: &BTreeMap<String, Variable>,
+    strict: bool,
+) -> Result<(), StateDictError> {
+    if strict {
+        let missing: Vec<String> = variables
+            .keys()
+            .filter(|key| !state_dict.contains_key(*key))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(StateDictError::MissingKeys(missing));
+        }
+        let unexpected: Vec<String> = state_dict
+            .keys()
+            .filter(|key| !variables.contains_key(*key))
+            .cloned()
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(StateDictError::UnexpectedKeys(unexpected));
+        }
+    }
+
+    for (key, value) in state_dict.iter() {
+        if let Some(variable) = variables.get(key) {
+            let shape = &variable.borrow().shape;
+            if *shape != value.shape {
+                return Err(StateDictError::ShapeMismatch {
+                    key: key.clone(),
+                    expected: shape.clone(),
+                    actual: value.shape.clone(),
+                });
+            }
+        }
+    }
+
+    for (key, value) in state_dict.iter() {
+        if let Some(variable) = variables.get(key) {
+            variable.borrow_mut().set_data(&value.data);
+        }
+    }
+    Ok(())
+}
+
+// path in the temporary directory, unique to the test process
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("miniature_{}_{}", std::process::id(), name))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
@@ -0,0 +1,208 @@
+// native checkpoint format
+//
+// all integers are little-endian
+//
+// | field    | type                | description                  |
+// |----------|---------------------|------------------------------|
+// | magic    | [u8; 4]             | b"MINI"                      |
+// | version  | u32                 | 2                            |
+// | metadata | u64                 | number of metadata entries   |
+//
+// followed by `metadata` string pairs sorted by key, which version 1 files
+// do not have
+//
+// | field   | type                | description                  |
+// |---------|---------------------|------------------------------|
+// | length  | u64                 | byte length of key           |
+// | key     | [u8; length]        | UTF-8 key (e.g. type)        |
+// | length  | u64                 | byte length of value         |
+// | value   | [u8; length]        | UTF-8 value (e.g. Adam)      |
+//
+// and then a u64 count of entries sorted by name
+//
+// | field   | type                | description                  |
+// |---------|---------------------|------------------------------|
+// | length  | u64                 | byte length of name          |
+// | name    | [u8; length]        | UTF-8 name (e.g. fc1.weight) |
+// | dtype   | u8                  | 0 for f32                    |
+// | ndim    | u64                 | number of dimensions         |
+// | shape   | [u64; ndim]         | size of each dimension       |
+// | data    | [f32; prod(shape)]  | values in C-order            |
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::rc::Rc;
+
+use super::StateDictError;
+use crate::variable::Variable;
+
+const MAGIC: &[u8; 4] = b"MINI";
+const VERSION: u32 = 2;
+const DTYPE_F32: u8 = 0;
+
+pub fn save(
+    path: &str,
+    variables: &BTreeMap<String, Rc<RefCell<Variable>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    save_with_metadata(path, variables, &BTreeMap::new())
+}
+
+fn write_string<W: Write>(file: &mut W, string: &str) -> Result<(), Box<dyn std::error::Error>> {
+    file.write_all(&(string.len() as u64).to_le_bytes())?;
+    file.write_all(string.as_bytes())?;
+    Ok(())
+}
+
+// metadata holds values that are not f32 tensors, such as names and exact
+// counters
+pub fn save_with_metadata(
+    path: &str,
+    variables: &BTreeMap<String, Rc<RefCell<Variable>>>,
+    metadata: &BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    // write header
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&(metadata.len() as u64).to_le_bytes())?;
+    for (key, value) in metadata.iter() {
+        write_string(&mut file, key)?;
+        write_string(&mut file, value)?;
+    }
+    file.write_all(&(variables.len() as u64).to_le_bytes())?;
+
+    // write entries
+    for (name, variable) in variables.iter() {
+        let variable = variable.borrow();
+        write_string(&mut file, name)?;
+        file.write_all(&[DTYPE_F32])?;
+        file.write_all(&(variable.shape.len() as u64).to_le_bytes())?;
+        for dim_size in variable.shape.iter() {
+            file.write_all(&(*dim_size as u64).to_le_bytes())?;
+        }
+        for value in variable.data.iter() {
+            file.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+fn read_u64<R: Read>(file: &mut R) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut buf = [0; 8];
+    file.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+// counts down the unread bytes so that sizes from a corrupt header are
+// rejected before anything is allocated for them
+fn consume(remaining: &mut u64, bytes: Option<u64>, field: &str) -> Result<(), StateDictError> {
+    match bytes {
+        Some(bytes) if bytes <= *remaining => {
+            *remaining -= bytes;
+            Ok(())
+        }
+        _ => Err(StateDictError::InvalidFormat(format!(
+            "{} exceeds the file size",
+            field
+        ))),
+    }
+}
+
+fn read_string<R: Read>(
+    file: &mut R,
+    remaining: &mut u64,
+    field: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    consume(remaining, Some(8), field)?;
+    let length = read_u64(file)?;
+    consume(remaining, Some(length), field)?;
+    let mut string = vec![0; length as usize];
+    file.read_exact(&mut string)?;
+    Ok(String::from_utf8(string)?)
+}
+
+pub fn load(path: &str) -> Result<BTreeMap<String, Variable>, Box<dyn std::error::Error>> {
+    Ok(load_with_metadata(path)?.0)
+}
+
+#[allow(clippy::type_complexity)]
+pub fn load_with_metadata(
+    path: &str,
+) -> Result<(BTreeMap<String, Variable>, BTreeMap<String, String>), Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut file = BufReader::new(file);
+
+    // read header
+    consume(&mut remaining, Some(8), "header")?;
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Box::new(StateDictError::InvalidFormat(
+            "wrong magic number".to_string(),
+        )));
+    }
+    let mut buf = [0; 4];
+    file.read_exact(&mut buf)?;
+    let version = u32::from_le_bytes(buf);
+    if version != 1 && version != VERSION {
+        return Err(Box::new(StateDictError::InvalidFormat(format!(
+            "unsupported version {}",
+            version
+        ))));
+    }
+    let mut metadata = BTreeMap::new();
+    if version >= 2 {
+        consume(&mut remaining, Some(8), "metadata count")?;
+        let count = read_u64(&mut file)?;
+        for _ in 0..count {
+            let key = read_string(&mut file, &mut remaining, "metadata key")?;
+            let value = read_string(&mut file, &mut remaining, &key)?;
+            metadata.insert(key, value);
+        }
+    }
+    consume(&mut remaining, Some(8), "count")?;
+    let count = read_u64(&mut file)?;
+
+    // read entries
+    let mut state_dict = BTreeMap::new();
+    for _ in 0..count {
+        let name = read_string(&mut file, &mut remaining, "name")?;
+
+        consume(&mut remaining, Some(1), "dtype")?;
+        let mut dtype = [0; 1];
+        file.read_exact(&mut dtype)?;
+        if dtype[0] != DTYPE_F32 {
+            return Err(Box::new(StateDictError::InvalidFormat(format!(
+                "unsupported dtype {} for {}",
+                dtype[0], name
+            ))));
+        }
+
+        consume(&mut remaining, Some(8), "rank")?;
+        let ndim = read_u64(&mut file)?;
+        consume(&mut remaining, ndim.checked_mul(8), "shape")?;
+        let mut shape = vec![];
+        for _ in 0..ndim {
+            shape.push(read_u64(&mut file)?);
+        }
+
+        let size = shape
+            .iter()
+            .try_fold(1u64, |size, dim_size| size.checked_mul(*dim_size));
+        consume(&mut remaining, size.and_then(|s| s.checked_mul(4)), &name)?;
+        let mut variable = Variable::new(shape.iter().map(|d| *d as usize).collect());
+        for value in variable.data.iter_mut() {
+            let mut buf = [0; 4];
+            file.read_exact(&mut buf)?;
+            *value = f32::from_le_bytes(buf);
+        }
+        state_dict.insert(name, variable);
+    }
+
+    Ok((state_dict, metadata))
+}
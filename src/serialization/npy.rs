@@ -0,0 +1,261 @@
+// NumPy .npy format
+// https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+//
+// variables are written as little-endian f32 in C-order, and f32, f64,
+// i32, i64 and u8 arrays are converted to f32 on reading
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use super::StateDictError;
+use crate::variable::Variable;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+fn invalid(message: String) -> Box<dyn std::error::Error> {
+    Box::new(StateDictError::InvalidFormat(message))
+}
+
+pub fn write<W: Write>(
+    writer: &mut W,
+    variable: &Variable,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shape = match variable.shape.len() {
+        1 => format!("({},)", variable.shape[0]),
+        _ => {
+            let dims: Vec<String> = variable.shape.iter().map(|d| d.to_string()).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+
+    // pad with spaces so that the data is aligned to 64 bytes
+    let preamble_size = MAGIC.len() + 2 + 2;
+    while !(preamble_size + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in variable.data.iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// returns the text following `'key': ` in the header dictionary
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, Box<dyn std::error::Error>> {
+    let pattern = format!("'{}':", key);
+    match header.find(&pattern) {
+        Some(position) => Ok(header[position + pattern.len()..].trim_start()),
+        None => Err(invalid(format!("missing {} in npy header", key))),
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, Vec<usize>), Box<dyn std::error::Error>> {
+    let descr = header_value(header, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|descr| descr.split('\'').next())
+        .ok_or_else(|| invalid("malformed descr in npy header".to_string()))?;
+
+    if header_value(header, "fortran_order")?.starts_with("True") {
+        return Err(invalid("fortran order is not supported".to_string()));
+    }
+
+    let shape = header_value(header, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|shape| shape.split(')').next())
+        .ok_or_else(|| invalid("malformed shape in npy header".to_string()))?;
+    let mut dims = vec![];
+    for dim in shape
+        .split(',')
+        .map(|dim| dim.trim())
+        .filter(|dim| !dim.is_empty())
+    {
+        dims.push(dim.parse::<usize>()?);
+    }
+
+    // scalars are represented with shape [1]
+    if dims.is_empty() {
+        dims.push(1);
+    }
+    Ok((descr.to_string(), dims))
+}
+
+// reads the reader to the end, which must hold exactly the data of the header
+pub fn read<R: Read>(reader: &mut R) -> Result<Variable, Box<dyn std::error::Error>> {
+    let mut magic = [0; 6];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("wrong magic number for npy".to_string()));
+    }
+
+    // version 1.0 has a 2 byte header length and later versions 4 bytes
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+    let header_len = match version[0] {
+        1 => {
+            let mut buf = [0; 2];
+            reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf) as usize
+        }
+        2 | 3 => {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf) as usize
+        }
+        major => return Err(invalid(format!("unsupported npy version {}", major))),
+    };
+    // lengths are bounded by the bytes actually read before allocating
+    let mut header = vec![];
+    reader.take(header_len as u64).read_to_end(&mut header)?;
+    if header.len() != header_len {
+        return Err(invalid("truncated npy header".to_string()));
+    }
+    let (descr, shape) = parse_header(&String::from_utf8(header)?)?;
+
+    let item_size = match descr.as_str() {
+        "<f4" | "<i4" => 4,
+        "<f8" | "<i8" => 8,
+        "|u1" => 1,
+        _ => return Err(invalid(format!("unsupported dtype {}", descr))),
+    };
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let data_size = shape.iter().try_fold(item_size, |size: usize, dim_size| {
+        size.checked_mul(*dim_size)
+    });
+    if data_size != Some(data.len()) {
+        return Err(invalid(format!(
+            "npy data of {} bytes does not match shape {:?}",
+            data.len(),
+            shape
+        )));
+    }
+
+    let mut variable = Variable::new(shape);
+    for (value, buf) in variable.data.iter_mut().zip(data.chunks_exact(item_size)) {
+        *value = match descr.as_str() {
+            "<f4" => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            "<i4" => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f32,
+            "<f8" => f64::from_le_bytes([
+                buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7],
+            ]) as f32,
+            "<i8" => i64::from_le_bytes([
+                buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7],
+            ]) as f32,
+            _ => buf[0] as f32,
+        };
+    }
+    Ok(variable)
+}
+
+pub fn save(path: &str, variable: &Variable) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write(&mut file, variable)?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn load(path: &str) -> Result<Variable, Box<dyn std::error::Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+    read(&mut file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read() {
+        let mut variable = Variable::new(vec![2, 3]);
+        variable.set_data(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let mut bytes = vec![];
+        write(&mut bytes, &variable).unwrap();
+        assert_eq!(bytes.len(), 128 + 6 * 4);
+        assert_eq!(
+            String::from_utf8_lossy(&bytes[10..128]).trim_end(),
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }"
+        );
+
+        let loaded = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.shape, vec![2, 3]);
+        assert_eq!(loaded.data, variable.data);
+    }
+
+    #[test]
+    fn read_other_dtypes() {
+        // np.array([[1, 2], [3, 4]], dtype=np.int64) with a hand written header
+        let header = "{'descr': '<i8', 'fortran_order': False, 'shape': (2, 2), }";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        for value in [1i64, 2, 3, 4] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let loaded = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.shape, vec![2, 2]);
+        assert_eq!(loaded.data, vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn read_scalar() {
+        let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (), }";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&2.5f64.to_le_bytes());
+
+        let loaded = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.shape, vec![1]);
+        assert_eq!(loaded.data, vec![2.5]);
+    }
+
+    #[test]
+    fn read_size_mismatch() {
+        let header = |shape: &str| {
+            let header = format!(
+                "{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}",
+                shape
+            );
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend_from_slice(&[1, 0]);
+            bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(header.as_bytes());
+            bytes.extend_from_slice(&[0; 8]);
+            bytes
+        };
+
+        // shapes that do not fit the data are rejected before allocating
+        for shape in [
+            "(3,)",
+            "(1,)",
+            "(4294967296, 4294967296)",
+            "(18446744073709551615, 2)",
+        ] {
+            let error = read(&mut header(shape).as_slice()).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<StateDictError>(),
+                Some(StateDictError::InvalidFormat(_))
+            ));
+        }
+        assert_eq!(read(&mut header("(2,)").as_slice()).unwrap().shape, vec![2]);
+
+        // header length beyond the data
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[2, 0]);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read(&mut bytes.as_slice()).is_err());
+    }
+}
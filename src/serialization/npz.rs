@@ -0,0 +1,349 @@
+// NumPy .npz format
+//
+// an uncompressed zip archive holding one .npy file per variable, as written
+// by numpy.savez
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+use super::{npy, StateDictError};
+use crate::variable::Variable;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+// 1980-01-01 in MS-DOS format
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+fn invalid(message: String) -> Box<dyn std::error::Error> {
+    Box::new(StateDictError::InvalidFormat(message))
+}
+
+// sizes, offsets and counts are limited to the fields of archives without
+// zip64 records, whose maximum values mark zip64
+fn too_large(field: &str, value: usize) -> Box<dyn std::error::Error> {
+    Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "{} of {} exceeds the zip format without zip64",
+            field, value
+        ),
+    ))
+}
+
+fn to_u16(value: usize, field: &str) -> Result<u16, Box<dyn std::error::Error>> {
+    match u16::try_from(value) {
+        Ok(converted) if converted != u16::MAX => Ok(converted),
+        _ => Err(too_large(field, value)),
+    }
+}
+
+fn to_u32(value: usize, field: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    match u32::try_from(value) {
+        Ok(converted) if converted != u32::MAX => Ok(converted),
+        _ => Err(too_large(field, value)),
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in bytes.iter() {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+pub fn save(
+    path: &str,
+    variables: &BTreeMap<String, Rc<RefCell<Variable>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let count = to_u16(variables.len(), "number of variables")?;
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut offset = 0;
+    let mut central_directory = vec![];
+
+    for (name, variable) in variables.iter() {
+        let name = format!("{}.npy", name);
+        let mut data = vec![];
+        npy::write(&mut data, &variable.borrow())?;
+        let crc = crc32(&data);
+        let size = to_u32(data.len(), "size")?;
+        let name_len = to_u16(name.len(), "name length")?;
+        let local_offset = to_u32(offset, "offset")?;
+
+        // fields shared by local and central headers
+        let mut fields = vec![];
+        fields.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        fields.extend_from_slice(&0u16.to_le_bytes()); // flags
+        fields.extend_from_slice(&0u16.to_le_bytes()); // stored
+        fields.extend_from_slice(&0u16.to_le_bytes()); // time
+        fields.extend_from_slice(&DOS_DATE.to_le_bytes());
+        fields.extend_from_slice(&crc.to_le_bytes());
+        fields.extend_from_slice(&size.to_le_bytes());
+        fields.extend_from_slice(&size.to_le_bytes());
+        fields.extend_from_slice(&name_len.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+        file.write_all(&LOCAL_HEADER_SIGNATURE.to_le_bytes())?;
+        file.write_all(&fields)?;
+        file.write_all(name.as_bytes())?;
+        file.write_all(&data)?;
+
+        central_directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central_directory.extend_from_slice(&fields);
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central_directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central_directory.extend_from_slice(&local_offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+
+        offset += 4 + fields.len() + name.len() + data.len();
+    }
+
+    let central_directory_size = to_u32(central_directory.len(), "central directory size")?;
+    let central_directory_offset = to_u32(offset, "offset")?;
+    file.write_all(&central_directory)?;
+    file.write_all(&END_SIGNATURE.to_le_bytes())?;
+    file.write_all(&0u16.to_le_bytes())?; // disk number
+    file.write_all(&0u16.to_le_bytes())?; // disk with central directory
+    file.write_all(&count.to_le_bytes())?;
+    file.write_all(&count.to_le_bytes())?;
+    file.write_all(&central_directory_size.to_le_bytes())?;
+    file.write_all(&central_directory_offset.to_le_bytes())?;
+    file.write_all(&0u16.to_le_bytes())?; // comment length
+
+    file.flush()?;
+    Ok(())
+}
+
+// offsets come from the archive, so additions are checked for overflow
+fn add(offset: usize, len: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    offset
+        .checked_add(len)
+        .ok_or_else(|| invalid("offset overflows in npz".to_string()))
+}
+
+fn read_bytes(
+    bytes: &[u8],
+    offset: usize,
+    len: usize,
+) -> Result<&[u8], Box<dyn std::error::Error>> {
+    bytes
+        .get(offset..add(offset, len)?)
+        .ok_or_else(|| invalid("unexpected end of npz".to_string()))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, Box<dyn std::error::Error>> {
+    let b = read_bytes(bytes, offset, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Box<dyn std::error::Error>> {
+    let b = read_bytes(bytes, offset, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, Box<dyn std::error::Error>> {
+    let b = read_bytes(bytes, offset, 8)?;
+    Ok(u64::from_le_bytes([
+        b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+    ]))
+}
+
+fn to_usize(value: u64) -> Result<usize, Box<dyn std::error::Error>> {
+    usize::try_from(value).map_err(|_| invalid("offset overflows in npz".to_string()))
+}
+
+pub fn load(path: &str) -> Result<BTreeMap<String, Variable>, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+
+    // the end of central directory record is searched backwards since it
+    // may be followed by a comment
+    let mut end = None;
+    for position in (0..(bytes.len().saturating_sub(21))).rev() {
+        if read_u32(&bytes, position)? == END_SIGNATURE {
+            end = Some(position);
+            break;
+        }
+    }
+    let end = end.ok_or_else(|| invalid("missing end of central directory".to_string()))?;
+    let count = read_u16(&bytes, end + 10)?;
+    let mut position = read_u32(&bytes, end + 16)? as usize;
+
+    // sizes in local headers may be deferred to data descriptors, so entries
+    // are located through the central directory
+    let mut variables = BTreeMap::new();
+    for _ in 0..count {
+        if read_u32(&bytes, position)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("wrong central directory signature".to_string()));
+        }
+        let header = read_bytes(&bytes, position, 46)?;
+        let method = read_u16(header, 10)?;
+        let crc = read_u32(header, 16)?;
+        let mut compressed_size = read_u32(header, 20)? as u64;
+        let mut size = read_u32(header, 24)? as u64;
+        let name_len = read_u16(header, 28)? as usize;
+        let extra_len = read_u16(header, 30)? as usize;
+        let comment_len = read_u16(header, 32)? as usize;
+        let mut local_offset = read_u32(header, 42)? as u64;
+        let name_start = position + 46;
+        let name = read_bytes(&bytes, name_start, name_len)?;
+        let name = String::from_utf8(name.to_vec())?;
+
+        // zip64 extra field holds the values saturated in the header
+        let extra_start = name_start + name_len;
+        let extra_field = read_bytes(&bytes, extra_start, extra_len)?;
+        let mut extra = 0;
+        while extra + 4 <= extra_len {
+            let id = read_u16(extra_field, extra)?;
+            let len = read_u16(extra_field, extra + 2)? as usize;
+            if id == ZIP64_EXTRA_ID {
+                let mut field = extra + 4;
+                for value in [&mut size, &mut compressed_size, &mut local_offset] {
+                    if *value == 0xffffffff {
+                        *value = read_u64(extra_field, field)?;
+                        field += 8;
+                    }
+                }
+            }
+            extra += 4 + len;
+        }
+        position = add(extra_start + extra_len, comment_len)?;
+
+        if method != 0 {
+            return Err(invalid(format!(
+                "compressed entry {} is not supported",
+                name
+            )));
+        }
+        let local_offset = to_usize(local_offset)?;
+        let local_header = read_bytes(&bytes, local_offset, 30)?;
+        if read_u32(local_header, 0)? != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid("wrong local header signature".to_string()));
+        }
+        let data_start = local_offset
+            + 30
+            + read_u16(local_header, 26)? as usize
+            + read_u16(local_header, 28)? as usize;
+        let data = read_bytes(&bytes, data_start, to_usize(size)?)?;
+        if crc32(data) != crc {
+            return Err(invalid(format!("crc mismatch for {}", name)));
+        }
+
+        let key = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+        variables.insert(key, npy::read(&mut &data[..])?);
+    }
+
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::temp_path;
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn save_load() {
+        let path = temp_path("arrays.npz");
+
+        let mut variables = BTreeMap::new();
+        for (name, shape) in [("fc.weight", vec![3, 2]), ("fc.bias", vec![2])] {
+            variables.insert(
+                name.to_string(),
+                Rc::new(RefCell::new(Variable::rand(shape))),
+            );
+        }
+        save(&path, &variables).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (name, variable) in variables.iter() {
+            assert_eq!(loaded[name].shape, variable.borrow().shape);
+            assert_eq!(loaded[name].data, variable.borrow().data);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_numpy_savez() {
+        // written by scripts/make_npz_fixture.py, with zip64 extra fields in
+        // local headers as numpy.savez does
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/savez.npz");
+        let loaded = load(path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded["a"].shape, vec![2, 3]);
+        assert_eq!(loaded["a"].data, vec![0.5, 1.0, 1.5, 2.0, 2.5, 3.0]);
+        assert_eq!(loaded["b"].shape, vec![3]);
+        assert_eq!(loaded["b"].data, vec![-1.5, 0.25, 1024.0]);
+        assert_eq!(loaded["c"].shape, vec![1]);
+        assert_eq!(loaded["c"].data, vec![7.0]);
+    }
+
+    #[test]
+    fn load_corrupt_offsets() {
+        let path = temp_path("corrupt.npz");
+
+        // a central directory entry whose zip64 values come from the extra field
+        let archive = |size: u64, local_offset: u64| {
+            let name = b"a.npy";
+            let mut bytes = CENTRAL_HEADER_SIGNATURE.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&[0; 16]);
+            bytes.extend_from_slice(&[0xff; 8]); // sizes
+            bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(&28u16.to_le_bytes()); // extra length
+            bytes.extend_from_slice(&[0; 10]);
+            bytes.extend_from_slice(&[0xff; 4]); // local offset
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(&ZIP64_EXTRA_ID.to_le_bytes());
+            bytes.extend_from_slice(&24u16.to_le_bytes());
+            for value in [size, size, local_offset] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            let central_directory_size = bytes.len() as u32;
+            bytes.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+            bytes.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+            bytes.extend_from_slice(&central_directory_size.to_le_bytes());
+            bytes.extend_from_slice(&[0; 6]);
+            bytes
+        };
+
+        // offsets and sizes near the maximum are rejected without overflowing
+        for (size, local_offset) in [(0, u64::MAX - 10), (u64::MAX - 10, 0), (1 << 40, 0)] {
+            std::fs::write(&path, archive(size, local_offset)).unwrap();
+            let error = load(&path).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<StateDictError>(),
+                Some(StateDictError::InvalidFormat(_))
+            ));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save_too_many_entries() {
+        let path = temp_path("entries.npz");
+
+        // the entry count does not fit without zip64
+        let mut variables = BTreeMap::new();
+        for i in 0..0xffff {
+            variables.insert(i.to_string(), Rc::new(RefCell::new(Variable::new(vec![1]))));
+        }
+        assert!(save(&path, &variables).is_err());
+        assert!(!std::path::Path::new(&path).exists());
+    }
+}
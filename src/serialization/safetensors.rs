@@ -0,0 +1,530 @@
+// safetensors format
+// https://github.com/huggingface/safetensors
+//
+// | field  | type        | description                                   |
+// |--------|-------------|-----------------------------------------------|
+// | size   | u64         | byte length of the header                     |
+// | header | [u8; size]  | JSON object mapping names to dtype, shape and |
+// |        |             | data_offsets relative to the buffer           |
+// | buffer | [u8]        | little-endian values in C-order               |
+//
+// variables are written as F32, and F32, F64, F16, BF16, I64, I32 and U8
+// tensors are converted to f32 on reading
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+use super::StateDictError;
+use crate::variable::Variable;
+
+// limits of the reference implementation for headers, which are a flat
+// object of entries with arrays as the deepest values
+const MAX_HEADER_SIZE: usize = 100_000_000;
+const MAX_DEPTH: usize = 8;
+
+fn invalid(message: String) -> Box<dyn std::error::Error> {
+    Box::new(StateDictError::InvalidFormat(message))
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn save(
+    path: &str,
+    variables: &BTreeMap<String, Rc<RefCell<Variable>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut entries = vec![];
+    let mut offset = 0;
+    for (name, variable) in variables.iter() {
+        let variable = variable.borrow();
+        let shape: Vec<String> = variable.shape.iter().map(|d| d.to_string()).collect();
+        let end = offset + variable.size() * 4;
+        entries.push(format!(
+            "\"{}\":{{\"dtype\":\"F32\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+            escape(name),
+            shape.join(","),
+            offset,
+            end
+        ));
+        offset = end;
+    }
+
+    // pad with spaces so that the buffer is aligned to 8 bytes
+    let mut header = format!("{{{}}}", entries.join(","));
+    while !header.len().is_multiple_of(8) {
+        header.push(' ');
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&(header.len() as u64).to_le_bytes())?;
+    file.write_all(header.as_bytes())?;
+    for variable in variables.values() {
+        for value in variable.borrow().data.iter() {
+            file.write_all(&value.to_le_bytes())?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+// recursive descent parser covering the JSON grammar, with nesting limited
+// so that deep headers cannot overflow the stack
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Result<u8, Box<dyn std::error::Error>> {
+        self.skip_whitespace();
+        match self.bytes.get(self.position) {
+            Some(byte) => Ok(*byte),
+            None => Err(invalid("unexpected end of header".to_string())),
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Box<dyn std::error::Error>> {
+        if self.peek()? != byte {
+            return Err(invalid(format!(
+                "expected '{}' at {} in header",
+                byte as char, self.position
+            )));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &str,
+        value: Json,
+    ) -> Result<Json, Box<dyn std::error::Error>> {
+        if !self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            return Err(invalid(format!(
+                "unexpected token at {} in header",
+                self.position
+            )));
+        }
+        self.position += literal.len();
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        match self.peek()? {
+            b'{' | b'[' => self.parse_nested(),
+            b'"' => Ok(Json::String(self.parse_string()?)),
+            b'n' => self.parse_literal("null", Json::Null),
+            b't' => self.parse_literal("true", Json::Bool(true)),
+            b'f' => self.parse_literal("false", Json::Bool(false)),
+            _ => self.parse_number(),
+        }
+    }
+
+    fn parse_nested(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid(format!(
+                "header is nested deeper than {} levels",
+                MAX_DEPTH
+            )));
+        }
+        self.depth += 1;
+        let value = if self.peek()? == b'{' {
+            self.parse_object()
+        } else {
+            self.parse_array()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        self.expect(b'{')?;
+        let mut object = BTreeMap::new();
+        if self.peek()? == b'}' {
+            self.position += 1;
+            return Ok(Json::Object(object));
+        }
+        loop {
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            object.insert(key, self.parse_value()?);
+            if self.peek()? == b',' {
+                self.position += 1;
+            } else {
+                self.expect(b'}')?;
+                return Ok(Json::Object(object));
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        self.expect(b'[')?;
+        let mut array = vec![];
+        if self.peek()? == b']' {
+            self.position += 1;
+            return Ok(Json::Array(array));
+        }
+        loop {
+            array.push(self.parse_value()?);
+            if self.peek()? == b',' {
+                self.position += 1;
+            } else {
+                self.expect(b']')?;
+                return Ok(Json::Array(array));
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.expect(b'"')?;
+        let mut bytes = vec![];
+        loop {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or_else(|| invalid("unterminated string in header".to_string()))?;
+            self.position += 1;
+            match byte {
+                b'"' => return Ok(String::from_utf8(bytes)?),
+                b'\\' => {
+                    let escaped = *self
+                        .bytes
+                        .get(self.position)
+                        .ok_or_else(|| invalid("unterminated string in header".to_string()))?;
+                    self.position += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.parse_unicode_escape()?,
+                        _ => return Err(invalid("invalid escape in header".to_string())),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, Box<dyn std::error::Error>> {
+        let hex = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| invalid("unterminated string in header".to_string()))?;
+        self.position += 4;
+        Ok(u32::from_str_radix(std::str::from_utf8(hex)?, 16)?)
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, Box<dyn std::error::Error>> {
+        let mut code = self.parse_hex()?;
+
+        // surrogate pairs encode characters outside the basic plane
+        if (0xd800..0xdc00).contains(&code) {
+            if !self.bytes[self.position..].starts_with(b"\\u") {
+                return Err(invalid("unpaired surrogate in header".to_string()));
+            }
+            self.position += 2;
+            let low = self.parse_hex()?;
+            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+        }
+        char::from_u32(code).ok_or_else(|| invalid("invalid unicode escape in header".to_string()))
+    }
+
+    fn parse_number(&mut self) -> Result<Json, Box<dyn std::error::Error>> {
+        let start = self.position;
+        while self.position < self.bytes.len()
+            && matches!(
+                self.bytes[self.position],
+                b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'
+            )
+        {
+            self.position += 1;
+        }
+        let number = std::str::from_utf8(&self.bytes[start..self.position])?;
+        match number.parse::<f64>() {
+            Ok(number) => Ok(Json::Number(number)),
+            Err(_) => Err(invalid(format!("unexpected token at {} in header", start))),
+        }
+    }
+}
+
+fn parse_json(bytes: &[u8]) -> Result<Json, Box<dyn std::error::Error>> {
+    let mut parser = Parser {
+        bytes,
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.position != bytes.len() {
+        return Err(invalid("trailing characters in header".to_string()));
+    }
+    Ok(value)
+}
+
+fn to_usizes(value: Option<&Json>, name: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let mut usizes = vec![];
+    if let Some(Json::Array(array)) = value {
+        for value in array.iter() {
+            match value {
+                Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => usizes.push(*n as usize),
+                _ => return Err(invalid(format!("invalid integer in header of {}", name))),
+            }
+        }
+        Ok(usizes)
+    } else {
+        Err(invalid(format!("missing array in header of {}", name)))
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        // subnormals are normalized for f32
+        (0, _) => {
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x3ff) << 13
+        }
+        (0x1f, _) => sign | 0x7f800000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+pub fn load(path: &str) -> Result<BTreeMap<String, Variable>, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    let header_size = match bytes.get(0..8) {
+        Some(b) => u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as usize,
+        None => return Err(invalid("missing header size".to_string())),
+    };
+    if header_size > MAX_HEADER_SIZE {
+        return Err(invalid(format!(
+            "header size {} exceeds the limit of {}",
+            header_size, MAX_HEADER_SIZE
+        )));
+    }
+    let header_end = header_size
+        .checked_add(8)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| invalid("header exceeds file size".to_string()))?;
+    let header = &bytes[8..header_end];
+    let buffer = &bytes[header_end..];
+
+    let header = match parse_json(header)? {
+        Json::Object(header) => header,
+        _ => return Err(invalid("header is not an object".to_string())),
+    };
+
+    let mut variables = BTreeMap::new();
+    for (name, entry) in header.iter() {
+        if name == "__metadata__" {
+            continue;
+        }
+        let entry = match entry {
+            Json::Object(entry) => entry,
+            _ => return Err(invalid(format!("entry {} is not an object", name))),
+        };
+        let dtype = match entry.get("dtype") {
+            Some(Json::String(dtype)) => dtype.as_str(),
+            _ => return Err(invalid(format!("missing dtype of {}", name))),
+        };
+        let mut shape = to_usizes(entry.get("shape"), name)?;
+        let offsets = to_usizes(entry.get("data_offsets"), name)?;
+
+        // scalars are represented with shape [1]
+        if shape.is_empty() {
+            shape.push(1);
+        }
+
+        let item_size = match dtype {
+            "F64" | "I64" => 8,
+            "F32" | "I32" => 4,
+            "F16" | "BF16" => 2,
+            "U8" => 1,
+            _ => return Err(invalid(format!("unsupported dtype {} of {}", dtype, name))),
+        };
+
+        // sizes are validated against the buffer before allocating
+        let data_size = shape.iter().try_fold(item_size, |size: usize, dim_size| {
+            size.checked_mul(*dim_size)
+        });
+        if offsets.len() != 2
+            || offsets[1] < offsets[0]
+            || Some(offsets[1] - offsets[0]) != data_size
+        {
+            return Err(invalid(format!("invalid data_offsets of {}", name)));
+        }
+        let data = buffer
+            .get(offsets[0]..offsets[1])
+            .ok_or_else(|| invalid(format!("data of {} exceeds file size", name)))?;
+        let mut variable = Variable::new(shape);
+
+        for (value, b) in variable.data.iter_mut().zip(data.chunks_exact(item_size)) {
+            *value = match dtype {
+                "F64" => {
+                    f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                }
+                "I64" => {
+                    i64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                }
+                "F32" => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                "I32" => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                "F16" => f16_to_f32(u16::from_le_bytes([b[0], b[1]])),
+                "BF16" => f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16),
+                _ => b[0] as f32,
+            };
+        }
+        variables.insert(name.clone(), variable);
+    }
+
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::temp_path;
+
+    #[test]
+    fn save_load() {
+        let path = temp_path("model.safetensors");
+        let mut variables = BTreeMap::new();
+        for (name, shape) in [("fc.weight", vec![3, 2]), ("fc.\"bias\"", vec![1, 2])] {
+            variables.insert(
+                name.to_string(),
+                Rc::new(RefCell::new(Variable::rand(shape))),
+            );
+        }
+        save(&path, &variables).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let header_size = u64::from_le_bytes(bytes[0..8].try_into().unwrap()) as usize;
+        assert_eq!(header_size % 8, 0);
+        assert_eq!(bytes.len(), 8 + header_size + 8 * 4);
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (name, variable) in variables.iter() {
+            assert_eq!(loaded[name].shape, variable.borrow().shape);
+            assert_eq!(loaded[name].data, variable.borrow().data);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_other_dtypes() {
+        let path = temp_path("half.safetensors");
+        let header = r#"{"__metadata__":{"format":"pt"},"a":{"dtype":"F16","shape":[3],"data_offsets":[0,6]},"b":{"dtype":"BF16","shape":[],"data_offsets":[6,8]}}"#;
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        // 1.0, -2.5 and the smallest subnormal in f16
+        for bits in [0x3c00u16, 0xc100, 0x0001] {
+            bytes.extend_from_slice(&bits.to_le_bytes());
+        }
+        // 1.5 in bf16
+        bytes.extend_from_slice(&0x3fc0u16.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded["a"].data, vec![1.0, -2.5, 2.0f32.powi(-24)]);
+        assert_eq!(loaded["b"].shape, vec![1]);
+        assert_eq!(loaded["b"].data, vec![1.5]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_corrupt_sizes() {
+        let path = temp_path("corrupt.safetensors");
+        let file = |header_size: u64, header: &str| {
+            let mut bytes = header_size.to_le_bytes().to_vec();
+            bytes.extend_from_slice(header.as_bytes());
+            bytes.extend_from_slice(&[0; 8]);
+            bytes
+        };
+
+        // sizes from the header are checked before slicing or allocating
+        let headers = [
+            (u64::MAX, "{}".to_string()),
+            (u64::MAX - 7, "{}".to_string()),
+            (1 << 40, "{}".to_string()),
+        ];
+        let entries = [
+            r#"{"a":{"dtype":"F32","shape":[1099511627776,1099511627776],"data_offsets":[0,8]}}"#,
+            r#"{"a":{"dtype":"F64","shape":[4611686018427387904,4],"data_offsets":[0,0]}}"#,
+            r#"{"a":{"dtype":"F32","shape":[4],"data_offsets":[0,16]}}"#,
+        ];
+        let entries = entries.iter().map(|e| (e.len() as u64, e.to_string()));
+        for (header_size, header) in headers.into_iter().chain(entries) {
+            std::fs::write(&path, file(header_size, &header)).unwrap();
+            let error = load(&path).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<StateDictError>(),
+                Some(StateDictError::InvalidFormat(_))
+            ));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parse_header() {
+        let json = parse_json(br#" {"a\u00e9\n": [1, -2.5e1, true, null], "b": {}} "#).unwrap();
+        let mut expected = BTreeMap::new();
+        expected.insert(
+            "a\u{e9}\n".to_string(),
+            Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null,
+            ]),
+        );
+        expected.insert("b".to_string(), Json::Object(BTreeMap::new()));
+        assert_eq!(json, Json::Object(expected));
+        assert!(parse_json(b"{\"a\": [1,]}").is_err());
+
+        // deep nesting is rejected instead of overflowing the stack
+        let deep = vec![b'['; 1_000_000];
+        assert!(parse_json(&deep).is_err());
+        let mut nested = vec![b'['; MAX_DEPTH];
+        nested.extend(vec![b']'; MAX_DEPTH]);
+        assert!(parse_json(&nested).is_ok());
+    }
+}
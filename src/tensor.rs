@@ -0,0 +1,351 @@
+use std::cell::{Ref, RefCell};
+use std::ops;
+use std::rc::Rc;
+
+use crate::functions as F;
+use crate::graph;
+use crate::variable::Variable;
+
+#[derive(Debug, Clone)]
+pub struct Tensor {
+    variable: Rc<RefCell<Variable>>,
+}
+
+impl Tensor {
+    pub fn new(shape: Vec<usize>) -> Self {
+        Self::from(Variable::new(shape))
+    }
+
+    pub fn rand(shape: Vec<usize>) -> Self {
+        Self::from(Variable::rand(shape))
+    }
+
+    pub fn from_data(shape: Vec<usize>, data: &[f32]) -> Self {
+        let mut variable = Variable::new(shape);
+        variable.set_data(data);
+        Self::from(variable)
+    }
+
+    pub fn full(shape: Vec<usize>, value: f32) -> Self {
+        let mut variable = Variable::new(shape);
+        variable.data.fill(value);
+        Self::from(variable)
+    }
+
+    pub fn variable(&self) -> Rc<RefCell<Variable>> {
+        self.variable.clone()
+    }
+
+    pub fn shape(&self) -> Vec<usize> {
+        self.variable.borrow().shape.clone()
+    }
+
+    pub fn size(&self) -> usize {
+        self.variable.borrow().size()
+    }
+
+    pub fn data(&self) -> Ref<'_, [f32]> {
+        Ref::map(self.variable.borrow(), |v| v.data.as_slice())
+    }
+
+    pub fn grad(&self) -> Ref<'_, [f32]> {
+        Ref::map(self.variable.borrow(), |v| v.grad.as_slice())
+    }
+
+    pub fn set_data(&self, data: &[f32]) {
+        self.variable.borrow_mut().set_data(data);
+    }
+
+    pub fn set_need_grad(&self, need_grad: bool) {
+        self.variable.borrow_mut().set_need_grad(need_grad);
+    }
+
+    pub fn zero_grad(&self) {
+        self.variable.borrow_mut().zero_grads();
+    }
+
+    pub fn avg_pool2d(
+        &self,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    ) -> Self {
+        Self::from(F::avg_pool2d(
+            self.variable.clone(),
+            kernel_size,
+            stride,
+            padding,
+        ))
+    }
+
+    pub fn backward(&self) {
+        graph::backward(self.variable.clone());
+    }
+
+    pub fn backward_with(&self, retain_graph: bool) {
+        graph::backward_with(self.variable.clone(), retain_graph);
+    }
+
+    pub fn detach(&self) -> Self {
+        Self::from(self.variable.borrow().detach())
+    }
+
+    pub fn argmax(&self) -> Self {
+        Self::from(F::argmax(self.variable.clone()))
+    }
+
+    pub fn global_avg_pool2d(&self) -> Self {
+        Self::from(F::global_avg_pool2d(self.variable.clone()))
+    }
+
+    pub fn log(&self) -> Self {
+        Self::from(F::log(self.variable.clone()))
+    }
+
+    pub fn log_softmax(&self) -> Self {
+        Self::from(F::log_softmax(self.variable.clone()))
+    }
+
+    pub fn logsumexp(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::logsumexp(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn matmul(&self, other: &Tensor) -> Self {
+        Self::from(F::matmul(self.variable.clone(), other.variable.clone()))
+    }
+
+    pub fn max(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::max(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn max_pool2d(
+        &self,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    ) -> Self {
+        Self::from(F::max_pool2d(
+            self.variable.clone(),
+            kernel_size,
+            stride,
+            padding,
+        ))
+    }
+
+    pub fn mean(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::mean(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn min(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::min(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn onehot(&self, num_classes: u32) -> Self {
+        Self::from(F::onehot(self.variable.clone(), num_classes))
+    }
+
+    pub fn prod(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::prod(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn relu(&self) -> Self {
+        Self::from(F::relu(self.variable.clone()))
+    }
+
+    pub fn reshape(&self, shape: Vec<usize>) -> Self {
+        Self::from(F::reshape(self.variable.clone(), shape))
+    }
+
+    pub fn softmax(&self) -> Self {
+        Self::from(F::softmax(self.variable.clone()))
+    }
+
+    pub fn sqrt(&self) -> Self {
+        Self::from(F::sqrt(self.variable.clone()))
+    }
+
+    pub fn square(&self) -> Self {
+        Self::from(F::square(self.variable.clone()))
+    }
+
+    pub fn std(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::std(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn sum(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::sum(self.variable.clone(), axes, keepdims))
+    }
+
+    pub fn var(&self, axes: &[usize], keepdims: bool) -> Self {
+        Self::from(F::var(self.variable.clone(), axes, keepdims))
+    }
+
+    // constant operand broadcast against the tensor
+    fn scalar(value: f32) -> Self {
+        let constant = Self::full(vec![1], value);
+        constant.set_need_grad(false);
+        constant
+    }
+}
+
+impl From<Variable> for Tensor {
+    fn from(variable: Variable) -> Self {
+        Self {
+            variable: Rc::new(RefCell::new(variable)),
+        }
+    }
+}
+
+impl From<Rc<RefCell<Variable>>> for Tensor {
+    fn from(variable: Rc<RefCell<Variable>>) -> Self {
+        Self { variable }
+    }
+}
+
+impl From<Tensor> for Rc<RefCell<Variable>> {
+    fn from(tensor: Tensor) -> Self {
+        tensor.variable
+    }
+}
+
+macro_rules! impl_binary_op {
+    ($trait:ident, $method:ident, $function:path) => {
+        impl ops::$trait<&Tensor> for &Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: &Tensor) -> Tensor {
+                Tensor::from($function(self.variable.clone(), rhs.variable.clone()))
+            }
+        }
+
+        impl ops::$trait<Tensor> for Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: Tensor) -> Tensor {
+                ops::$trait::$method(&self, &rhs)
+            }
+        }
+
+        impl ops::$trait<&Tensor> for Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: &Tensor) -> Tensor {
+                ops::$trait::$method(&self, rhs)
+            }
+        }
+
+        impl ops::$trait<Tensor> for &Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: Tensor) -> Tensor {
+                ops::$trait::$method(self, &rhs)
+            }
+        }
+
+        impl ops::$trait<f32> for &Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: f32) -> Tensor {
+                ops::$trait::$method(self, &Tensor::scalar(rhs))
+            }
+        }
+
+        impl ops::$trait<f32> for Tensor {
+            type Output = Tensor;
+
+            fn $method(self, rhs: f32) -> Tensor {
+                ops::$trait::$method(&self, rhs)
+            }
+        }
+
+        impl ops::$trait<&Tensor> for f32 {
+            type Output = Tensor;
+
+            fn $method(self, rhs: &Tensor) -> Tensor {
+                ops::$trait::$method(&Tensor::scalar(self), rhs)
+            }
+        }
+
+        impl ops::$trait<Tensor> for f32 {
+            type Output = Tensor;
+
+            fn $method(self, rhs: Tensor) -> Tensor {
+                ops::$trait::$method(self, &rhs)
+            }
+        }
+    };
+}
+
+impl_binary_op!(Add, add, F::add);
+impl_binary_op!(Sub, sub, F::sub);
+impl_binary_op!(Mul, mul, F::mul);
+impl_binary_op!(Div, div, F::div);
+
+impl ops::Neg for &Tensor {
+    type Output = Tensor;
+
+    fn neg(self) -> Tensor {
+        Tensor::from(F::neg(self.variable.clone()))
+    }
+}
+
+impl ops::Neg for Tensor {
+    type Output = Tensor;
+
+    fn neg(self) -> Tensor {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tensor_operators() {
+        let x = Tensor::from_data(vec![1, 3], &[1.0, 2.0, 3.0]);
+        let y = Tensor::from_data(vec![1, 3], &[4.0, 5.0, 6.0]);
+
+        assert_eq!(&*(&x + &y).data(), &[5.0, 7.0, 9.0]);
+        assert_eq!(&*(&x - &y).data(), &[-3.0, -3.0, -3.0]);
+        assert_eq!(&*(&x * &y).data(), &[4.0, 10.0, 18.0]);
+        assert_eq!(&*(&y / &x).data(), &[4.0, 2.5, 2.0]);
+        assert_eq!(&*(-&x).data(), &[-1.0, -2.0, -3.0]);
+    }
+
+    #[test]
+    fn tensor_scalar_operators() {
+        let x = Tensor::from_data(vec![1, 3], &[1.0, 2.0, 4.0]);
+
+        assert_eq!(&*(&x + 1.0).data(), &[2.0, 3.0, 5.0]);
+        assert_eq!(&*(1.0 - &x).data(), &[0.0, -1.0, -3.0]);
+        assert_eq!(&*(&x * 2.0).data(), &[2.0, 4.0, 8.0]);
+        assert_eq!(&*(4.0 / &x).data(), &[4.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn tensor_backward() {
+        let x = Tensor::from_data(vec![1, 2], &[1.0, 2.0]);
+        let w = Tensor::from_data(vec![2, 2], &[1.0, 0.0, 0.0, 1.0]);
+
+        // y = mean(relu(x @ w) * 3 - x)
+        let y = ((x.matmul(&w).relu() * 3.0) - &x).mean(&[], false);
+        y.backward();
+
+        assert_eq!(y.shape(), vec![1]);
+        assert_eq!(&*y.data(), &[3.0]);
+        assert_eq!(&*x.grad(), &[1.0, 1.0]);
+        assert_eq!(&*w.grad(), &[1.5, 1.5, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn tensor_detach() {
+        let x = Tensor::from_data(vec![2], &[1.0, 2.0]);
+
+        // y = sum(x * stop_gradient(x))
+        let y = (&x * x.detach()).sum(&[], false);
+        y.backward_with(true);
+        y.backward();
+        assert_eq!(&*x.grad(), &[2.0, 4.0]);
+    }
+}
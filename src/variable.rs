@@ -3,6 +3,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::function::CgFunction;
+use crate::random;
 
 #[derive(Debug)]
 pub struct Variable {
@@ -20,8 +21,8 @@ impl Variable {
             size *= dim_size;
         }
 
-        let data = vec![0.0; size as usize];
-        let grad = vec![0.0; size as usize];
+        let data = vec![0.0; size];
+        let grad = vec![0.0; size];
 
         Self {
             parent: None,
@@ -33,10 +34,13 @@ impl Variable {
     }
 
     pub fn rand(shape: Vec<usize>) -> Self {
+        random::with_rng(|rng| Self::rand_with(shape, rng))
+    }
+
+    pub fn rand_with<R: Rng>(shape: Vec<usize>, rng: &mut R) -> Self {
         let mut variable = Self::new(shape);
 
         // randomly initialize weight
-        let mut rng = rand::thread_rng();
         for i in 0..variable.size() {
             variable.data[i] = rng.gen();
             variable.data[i] -= 0.5;
@@ -86,6 +90,14 @@ impl Variable {
     pub fn set_parent(&mut self, parent: Rc<RefCell<CgFunction>>) {
         self.parent = Some(parent);
     }
+
+    // copy of data without history, treated as a constant
+    pub fn detach(&self) -> Self {
+        let mut variable = Self::new(self.shape.clone());
+        variable.set_data(&self.data);
+        variable.set_need_grad(false);
+        variable
+    }
 }
 
 #[cfg(test)]